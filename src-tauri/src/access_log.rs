@@ -0,0 +1,354 @@
+//! 代理访问日志模块
+//! 
+//! 按请求记录代理服务器的方法、区间、上游主机与状态码、传输字节数、耗时和客户端中断情况，
+//! 以 JSON Lines 格式写入应用日志目录（超过大小后轮转），供排查播放卡顿时查询
+
+use crate::constants::{
+    ACCESS_LOG_FILE_NAME, ACCESS_LOG_MAX_BYTES, ACCESS_LOG_MAX_FILES, ACCESS_LOG_QUERY_LIMIT,
+};
+use crate::paths;
+use axum::{
+    body::{Body, HttpBody},
+    extract::Request,
+    http::{header, Method},
+    middleware::Next,
+    response::Response,
+};
+use futures::StreamExt;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::future::Future;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// 一条访问日志
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessLogEntry {
+    /// 请求时间（Unix 毫秒）
+    pub timestamp: u64,
+    pub method: String,
+    /// 请求路径（访问令牌已隐去）
+    pub path: String,
+    /// 客户端请求的 Range
+    pub range: Option<String>,
+    /// 返回给客户端的状态码
+    pub status: u16,
+    pub upstream_host: Option<String>,
+    pub upstream_status: Option<u16>,
+    /// 是否命中预取缓存
+    #[serde(default)]
+    pub cache_hit: bool,
+    /// 实际发送给客户端的字节数
+    pub bytes: u64,
+    /// 从收到请求到响应结束的耗时（毫秒）
+    pub duration_ms: u64,
+    /// 客户端是否在响应结束前断开
+    pub aborted: bool,
+    pub error: Option<String>,
+}
+
+/// 访问日志查询条件
+#[derive(Debug, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessLogQuery {
+    /// 最多返回的条数（默认 200）
+    pub limit: Option<usize>,
+    /// 只返回失败或中断的请求
+    #[serde(default)]
+    pub errors_only: bool,
+    /// 路径需包含的内容（如 bvid）
+    pub path_contains: Option<String>,
+    /// 只返回该时间（Unix 毫秒）之后的请求
+    pub since: Option<u64>,
+}
+
+/// 请求处理过程中收集的上游信息
+#[derive(Debug, Default)]
+struct TraceState {
+    upstream_host: Option<String>,
+    upstream_status: Option<u16>,
+    cache_hit: bool,
+    error: Option<String>,
+}
+
+/// 当前请求的追踪上下文
+#[derive(Debug, Clone, Default)]
+pub struct RequestTrace(Arc<Mutex<TraceState>>);
+
+tokio::task_local! {
+    static CURRENT_TRACE: RequestTrace;
+}
+
+lazy_static! {
+    /// 日志写入线程的发送端（写文件和轮转不占用异步运行时）
+    static ref LOG_WRITER: Mutex<mpsc::Sender<AccessLogEntry>> = Mutex::new(spawn_writer());
+}
+
+/// 获取当前请求的追踪上下文（不在代理请求中时返回 None）
+pub fn current() -> Option<RequestTrace> {
+    CURRENT_TRACE.try_with(|trace| trace.clone()).ok()
+}
+
+/// 在指定追踪上下文中执行（用于在后台任务中继续记录原请求的上游信息）
+pub async fn with_trace<F: Future>(trace: Option<RequestTrace>, future: F) -> F::Output {
+    match trace {
+        Some(trace) => CURRENT_TRACE.scope(trace, future).await,
+        None => future.await,
+    }
+}
+
+fn update(f: impl FnOnce(&mut TraceState)) {
+    let _ = CURRENT_TRACE.try_with(|trace| {
+        if let Ok(mut state) = trace.0.lock() {
+            f(&mut state);
+        }
+    });
+}
+
+/// 记录上游请求的主机和状态码（网络错误时状态码为 None）
+pub fn record_upstream(url: &str, status: Option<u16>) {
+    let host = reqwest::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(|host| host.to_string()));
+    update(|state| {
+        state.upstream_host = host;
+        state.upstream_status = status;
+    });
+}
+
+/// 记录命中预取缓存
+pub fn record_cache_hit() {
+    update(|state| state.cache_hit = true);
+}
+
+/// 记录请求失败原因
+pub fn record_error(error: impl Into<String>) {
+    let error = error.into();
+    update(|state| state.error = Some(error));
+}
+
+/// 隐去路径中的访问令牌（查询参数整体省略）
+fn redact_path(path: &str) -> String {
+    let mut segments: Vec<&str> = path.split('/').collect();
+    if matches!(segments.get(1), Some(&"proxy") | Some(&"play") | Some(&"dlna")) && segments.len() > 2 {
+        segments[2] = "***";
+    }
+    segments.join("/")
+}
+
+/// 尚未结束的请求（释放时写入日志，响应体未发送完即释放视为客户端中断）
+struct PendingEntry {
+    timestamp: u64,
+    method: String,
+    path: String,
+    range: Option<String>,
+    status: u16,
+    trace: RequestTrace,
+    started_at: Instant,
+    bytes: u64,
+    completed: bool,
+    body_error: Option<String>,
+}
+
+impl Drop for PendingEntry {
+    fn drop(&mut self) {
+        let state = self.trace.0.lock().map(|mut s| std::mem::take(&mut *s)).unwrap_or_default();
+        let error = self.body_error.take().or(state.error);
+        write(AccessLogEntry {
+            timestamp: self.timestamp,
+            method: std::mem::take(&mut self.method),
+            path: std::mem::take(&mut self.path),
+            range: self.range.take(),
+            status: self.status,
+            upstream_host: state.upstream_host,
+            upstream_status: state.upstream_status,
+            cache_hit: state.cache_hit,
+            bytes: self.bytes,
+            duration_ms: self.started_at.elapsed().as_millis() as u64,
+            aborted: !self.completed && error.is_none(),
+            error,
+        });
+    }
+}
+
+/// 访问日志中间件（在响应体发送结束或客户端断开时记录）
+pub async fn middleware(request: Request, next: Next) -> Response {
+    let started_at = Instant::now();
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    let is_head = request.method() == Method::HEAD;
+    let method = request.method().to_string();
+    let path = redact_path(request.uri().path());
+    let range = request
+        .headers()
+        .get("range")
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string());
+    
+    let trace = RequestTrace::default();
+    let response = CURRENT_TRACE.scope(trace.clone(), next.run(request)).await;
+    
+    let mut pending = PendingEntry {
+        timestamp,
+        method,
+        path,
+        range,
+        status: response.status().as_u16(),
+        trace,
+        started_at,
+        bytes: 0,
+        completed: false,
+        body_error: None,
+    };
+    if is_head || response.body().is_end_stream() {
+        pending.completed = true;
+        drop(pending);
+        return response;
+    }
+    
+    // 已知长度的响应体发送完最后一块后不会再被读取，按长度判断是否发送完整
+    let expected = response
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+    let (parts, body) = response.into_parts();
+    let stream = futures::stream::unfold(
+        (body.into_data_stream(), pending),
+        move |(mut stream, mut pending)| async move {
+            match stream.next().await {
+                Some(result) => {
+                    match &result {
+                        Ok(chunk) => {
+                            pending.bytes += chunk.len() as u64;
+                            pending.completed = expected == Some(pending.bytes);
+                        }
+                        Err(e) => pending.body_error = Some(e.to_string()),
+                    }
+                    Some((result, (stream, pending)))
+                }
+                None => {
+                    pending.completed = true;
+                    drop(pending);
+                    None
+                }
+            }
+        },
+    );
+    Response::from_parts(parts, Body::from_stream(stream))
+}
+
+fn log_path(index: usize) -> Result<PathBuf, String> {
+    match index {
+        0 => paths::log_file(ACCESS_LOG_FILE_NAME),
+        n => paths::log_file(&format!("{}.{}", ACCESS_LOG_FILE_NAME, n)),
+    }
+}
+
+fn write(entry: AccessLogEntry) {
+    if let Ok(writer) = LOG_WRITER.lock() {
+        let _ = writer.send(entry);
+    }
+}
+
+/// 启动日志写入线程
+fn spawn_writer() -> mpsc::Sender<AccessLogEntry> {
+    let (tx, rx) = mpsc::channel::<AccessLogEntry>();
+    std::thread::spawn(move || {
+        for entry in rx {
+            if let Err(e) = append(&entry) {
+                eprintln!("[AccessLog] 写入访问日志失败: {}", e);
+            }
+        }
+    });
+    tx
+}
+
+/// 追加一条日志（当前文件超过大小时先轮转）
+fn append(entry: &AccessLogEntry) -> Result<(), String> {
+    let path = log_path(0)?;
+    if fs::metadata(&path).map(|m| m.len() >= ACCESS_LOG_MAX_BYTES).unwrap_or(false) {
+        rotate()?;
+    }
+    
+    let mut line = serde_json::to_string(entry).map_err(|e| e.to_string())?;
+    line.push('\n');
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(line.as_bytes()))
+        .map_err(|e| e.to_string())
+}
+
+/// 轮转日志文件（proxy-access.log → proxy-access.log.1 → …，超出保留数的删除）
+fn rotate() -> Result<(), String> {
+    let _ = fs::remove_file(log_path(ACCESS_LOG_MAX_FILES)?);
+    for index in (0..ACCESS_LOG_MAX_FILES).rev() {
+        let from = log_path(index)?;
+        if from.exists() {
+            fs::rename(&from, log_path(index + 1)?).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+/// 查询访问日志（按时间从新到旧）
+pub async fn query(query: AccessLogQuery) -> Result<Vec<AccessLogEntry>, String> {
+    tokio::task::spawn_blocking(move || {
+        let limit = query.limit.unwrap_or(ACCESS_LOG_QUERY_LIMIT);
+        let mut entries = Vec::new();
+        for index in 0..=ACCESS_LOG_MAX_FILES {
+            let content = match fs::read_to_string(log_path(index)?) {
+                Ok(content) => content,
+                Err(_) => continue,
+            };
+            for line in content.lines().rev() {
+                if entries.len() >= limit {
+                    return Ok(entries);
+                }
+                let entry: AccessLogEntry = match serde_json::from_str(line) {
+                    Ok(entry) => entry,
+                    Err(_) => continue,
+                };
+                // 记录在响应结束时写入，时间戳不严格有序，不能提前结束
+                if query.since.map(|since| entry.timestamp < since).unwrap_or(false) {
+                    continue;
+                }
+                if query.errors_only
+                    && entry.status < 400
+                    && !entry.aborted
+                    && entry.error.is_none()
+                {
+                    continue;
+                }
+                if let Some(needle) = &query.path_contains {
+                    if !entry.path.contains(needle.as_str()) {
+                        continue;
+                    }
+                }
+                entries.push(entry);
+            }
+        }
+        Ok(entries)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// 清空访问日志
+pub fn clear() -> Result<(), String> {
+    for index in 0..=ACCESS_LOG_MAX_FILES {
+        let path = log_path(index)?;
+        if path.exists() {
+            fs::remove_file(&path).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,159 @@
+//! 接口响应缓存模块
+//! 
+//! 按接口设置缓存时间（视频详情 1 天、搜索 10 分钟），以去掉签名参数并排序后的 URL 为键，
+//! 缓存 code 为 0 的响应到磁盘，桌面端 http_request 和 bilibili 模块共用
+
+use crate::constants::{API_CACHE_MAX_FILES, API_CACHE_SEARCH_TTL_SECS, API_CACHE_VIEW_TTL_SECS};
+use crate::paths;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 磁盘上的缓存条目
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CacheEntry {
+    /// 缓存键（用于排除哈希冲突）
+    key: String,
+    /// 写入时间（Unix 秒）
+    stored_at: u64,
+    response: serde_json::Value,
+}
+
+/// 接口的缓存时间（秒），不缓存的接口返回 None
+fn ttl_for(path: &str) -> Option<u64> {
+    match path {
+        "/x/web-interface/view" => Some(API_CACHE_VIEW_TTL_SECS),
+        "/x/web-interface/search/type" | "/x/web-interface/wbi/search/type" => Some(API_CACHE_SEARCH_TTL_SECS),
+        _ => None,
+    }
+}
+
+/// 计算缓存键和缓存时间
+///
+/// 去掉 WBI 签名参数（每次请求都不同），参数按名称排序；
+/// 新旧搜索接口参数相同，统一使用同一个键
+fn cache_key(url: &str, params: &[(String, String)]) -> Option<(String, u64)> {
+    let mut url = reqwest::Url::parse(url).ok()?;
+    let ttl = ttl_for(url.path())?;
+    if url.path() == "/x/web-interface/wbi/search/type" {
+        url.set_path("/x/web-interface/search/type");
+    }
+    
+    let mut pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .chain(params.iter().cloned())
+        .filter(|(key, _)| key != "w_rid" && key != "wts")
+        .collect();
+    pairs.sort();
+    url.set_query(None);
+    let query = pairs
+        .iter()
+        .map(|(key, value)| format!("{}={}", urlencoding::encode(key), urlencoding::encode(value)))
+        .collect::<Vec<_>>()
+        .join("&");
+    Some((format!("{}?{}", url, query), ttl))
+}
+
+/// 缓存文件路径（文件名为缓存键的 SHA-256，不随编译器版本变化）
+fn cache_path(key: &str) -> Result<PathBuf, String> {
+    let digest = Sha256::digest(key.as_bytes());
+    Ok(paths::cache_dir("api")?.join(format!("{:x}.json", digest)))
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// 读取未过期的缓存（过期的文件顺便删除）
+fn load_blocking(key: &str, ttl: u64) -> Option<serde_json::Value> {
+    let path = cache_path(key).ok()?;
+    let data = fs::read(&path).ok()?;
+    let entry: CacheEntry = serde_json::from_slice(&data).ok()?;
+    if entry.key != key {
+        return None;
+    }
+    if unix_now().saturating_sub(entry.stored_at) >= ttl {
+        let _ = fs::remove_file(&path);
+        return None;
+    }
+    Some(entry.response)
+}
+
+/// 写入缓存（先写临时文件再重命名）
+fn store_blocking(key: String, response: serde_json::Value) -> Result<(), String> {
+    let path = cache_path(&key)?;
+    let entry = CacheEntry {
+        key,
+        stored_at: unix_now(),
+        response,
+    };
+    let data = serde_json::to_vec(&entry).map_err(|e| e.to_string())?;
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, data).map_err(|e| e.to_string())?;
+    fs::rename(&tmp, &path).map_err(|e| e.to_string())?;
+    
+    if let Some(dir) = path.parent() {
+        prune(dir);
+    }
+    Ok(())
+}
+
+/// 缓存文件超过上限时删除最旧的文件
+fn prune(dir: &Path) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    let mut files: Vec<_> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let modified = entry.metadata().ok()?.modified().ok()?;
+            Some((modified, entry.path()))
+        })
+        .collect();
+    if files.len() <= API_CACHE_MAX_FILES {
+        return;
+    }
+    files.sort_by_key(|(modified, _)| *modified);
+    let excess = files.len() - API_CACHE_MAX_FILES;
+    for (_, path) in files.into_iter().take(excess) {
+        let _ = fs::remove_file(path);
+    }
+}
+
+/// 查询缓存的响应（接口不缓存或未命中时返回 None）
+pub async fn load(url: &str, params: &[(String, String)]) -> Option<serde_json::Value> {
+    let (key, ttl) = cache_key(url, params)?;
+    tokio::task::spawn_blocking(move || load_blocking(&key, ttl))
+        .await
+        .ok()
+        .flatten()
+}
+
+/// 缓存响应（只缓存 code 为 0 的响应，接口不缓存时忽略）
+pub async fn store(url: &str, params: &[(String, String)], response: &serde_json::Value) {
+    if response["code"].as_i64() != Some(0) {
+        return;
+    }
+    let (key, _) = match cache_key(url, params) {
+        Some(key) => key,
+        None => return,
+    };
+    let response = response.clone();
+    if let Ok(Err(e)) = tokio::task::spawn_blocking(move || store_blocking(key, response)).await {
+        eprintln!("[ApiCache] 写入缓存失败: {}", e);
+    }
+}
+
+/// 清空接口缓存
+pub fn clear() -> Result<(), String> {
+    let dir = paths::cache_dir("api")?;
+    fs::remove_dir_all(&dir).map_err(|e| format!("清空接口缓存失败: {}", e))
+}
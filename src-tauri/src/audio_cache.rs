@@ -0,0 +1,72 @@
+//! 音频预取缓存模块
+//! 
+//! 缓存即将播放曲目的前几 MB 数据，切歌时可直接从内存返回开头部分
+
+use axum::body::Bytes;
+use lazy_static::lazy_static;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use crate::constants::AUDIO_PREFETCH_CACHE_CAPACITY;
+
+/// 已缓存的音频开头数据
+#[derive(Debug)]
+pub struct CachedPrefix {
+    /// 从第 0 字节开始的连续数据
+    pub data: Bytes,
+    /// 完整文件长度
+    pub total_length: u64,
+    pub content_type: String,
+}
+
+// 预取缓存（按插入顺序淘汰，容量很小，线性查找即可）
+lazy_static! {
+    static ref AUDIO_PREFIX_CACHE: Mutex<VecDeque<(String, Arc<CachedPrefix>)>> =
+        Mutex::new(VecDeque::new());
+}
+
+/// 计算缓存键
+///
+/// B 站音频链接的查询参数（签名、过期时间）和 CDN 主机每次解析都会变化，
+/// 但路径唯一标识同一个音频流，因此只使用路径作为键
+pub fn cache_key(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(parsed) => parsed.path().to_string(),
+        Err(_) => url.to_string(),
+    }
+}
+
+/// 查找缓存
+pub fn get(url: &str) -> Option<Arc<CachedPrefix>> {
+    let key = cache_key(url);
+    let cache = AUDIO_PREFIX_CACHE.lock().ok()?;
+    cache
+        .iter()
+        .find(|(k, _)| *k == key)
+        .map(|(_, prefix)| prefix.clone())
+}
+
+/// 判断是否已缓存
+pub fn contains(url: &str) -> bool {
+    get(url).is_some()
+}
+
+/// 删除缓存（上游文件与缓存不一致时）
+pub fn remove(url: &str) {
+    let key = cache_key(url);
+    if let Ok(mut cache) = AUDIO_PREFIX_CACHE.lock() {
+        cache.retain(|(k, _)| *k != key);
+    }
+}
+
+/// 写入缓存（超出容量时淘汰最早的条目）
+pub fn insert(url: &str, prefix: CachedPrefix) {
+    let key = cache_key(url);
+    if let Ok(mut cache) = AUDIO_PREFIX_CACHE.lock() {
+        cache.retain(|(k, _)| *k != key);
+        cache.push_back((key, Arc::new(prefix)));
+        while cache.len() > AUDIO_PREFETCH_CACHE_CAPACITY {
+            cache.pop_front();
+        }
+    }
+}
@@ -0,0 +1,483 @@
+//! B 站接口模块
+//! 
+//! 搜索、视频详情和播放地址接口的类型化实现，桌面端和 Android 共用，
+//! 基于全局 HTTP 客户端（共享 Cookie 和 B 站请求头）
+
+use crate::constants::{BILIBILI_API_BASE, BILIBILI_SEARCH_PAGE_SIZE};
+use crate::http_client::{add_bilibili_headers, get_http_client};
+use crate::{api_cache, risk_control, wbi};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// 接口通用响应（data 在确认 code 为 0 后再按具体类型解析）
+#[derive(Debug, Deserialize)]
+struct ApiResponse {
+    code: i64,
+    #[serde(default)]
+    message: String,
+    #[serde(default)]
+    data: serde_json::Value,
+}
+
+/// 接口错误（前端根据 kind 区分风控和其他错误）
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ApiError {
+    /// 触发风控（HTTP 412 或 code -412/-352），需要等待 retry_after_secs 秒后再请求
+    #[serde(rename_all = "camelCase")]
+    RiskControl {
+        code: i64,
+        retry_after_secs: u64,
+        message: String,
+    },
+    Other { message: String },
+}
+
+impl ApiError {
+    /// 记录风控并生成对应的错误
+    fn risk_control(code: i64) -> Self {
+        let retry_after_secs = risk_control::report_blocked(code);
+        ApiError::RiskControl {
+            code,
+            retry_after_secs,
+            message: format!("请求过于频繁，已被B站风控 ({})，请 {} 秒后重试", code, retry_after_secs),
+        }
+    }
+    
+    /// 冷却中，请求未发出
+    fn cooling_down(retry_after_secs: u64) -> Self {
+        ApiError::RiskControl {
+            code: -412,
+            retry_after_secs,
+            message: format!("B站风控冷却中，请 {} 秒后重试", retry_after_secs),
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::RiskControl { message, .. } | ApiError::Other { message } => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl From<String> for ApiError {
+    fn from(message: String) -> Self {
+        ApiError::Other { message }
+    }
+}
+
+impl From<ApiError> for String {
+    fn from(error: ApiError) -> Self {
+        error.to_string()
+    }
+}
+
+/// 搜索结果中的视频
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchVideo {
+    pub bvid: String,
+    /// 标题（已去除高亮标签）
+    pub title: String,
+    /// 封面地址（已补全为 https）
+    pub pic: String,
+    pub author: String,
+    /// UP 主 mid
+    pub mid: u64,
+    /// 时长（秒）
+    pub duration: u64,
+    /// 播放量（接口未返回时为 None）
+    pub play: Option<u64>,
+}
+
+/// 搜索结果排序
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchOrder {
+    /// 综合排序
+    #[default]
+    Totalrank,
+    /// 最多播放
+    Click,
+    /// 最新发布
+    Pubdate,
+}
+
+impl SearchOrder {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SearchOrder::Totalrank => "totalrank",
+            SearchOrder::Click => "click",
+            SearchOrder::Pubdate => "pubdate",
+        }
+    }
+}
+
+/// 一页搜索结果
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchPage {
+    pub page: u32,
+    pub num_pages: u32,
+    pub num_results: u64,
+    pub videos: Vec<SearchVideo>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawSearchPage {
+    #[serde(default)]
+    page: u32,
+    #[serde(default)]
+    num_pages: u32,
+    #[serde(default)]
+    num_results: u64,
+    #[serde(default)]
+    result: Option<Vec<RawSearchVideo>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RawSearchVideo {
+    #[serde(default)]
+    bvid: String,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    pic: String,
+    #[serde(default)]
+    author: String,
+    #[serde(default)]
+    mid: u64,
+    /// 搜索接口返回 "mm:ss" 字符串，个别情况下为秒数
+    #[serde(default)]
+    duration: serde_json::Value,
+    /// 播放量，无数据时为 "--"
+    #[serde(default)]
+    play: serde_json::Value,
+}
+
+/// UP 主信息
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Owner {
+    pub mid: u64,
+    pub name: String,
+    #[serde(default)]
+    pub face: String,
+}
+
+/// 视频分 P
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VideoPage {
+    pub cid: u64,
+    pub page: u32,
+    pub part: String,
+    /// 时长（秒）
+    pub duration: u64,
+}
+
+/// 视频详情（/x/web-interface/view）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VideoInfo {
+    pub bvid: String,
+    pub aid: u64,
+    /// 第一个分 P 的 cid
+    pub cid: u64,
+    pub title: String,
+    pub pic: String,
+    #[serde(default)]
+    pub desc: String,
+    /// 总时长（秒）
+    pub duration: u64,
+    #[serde(default)]
+    pub pubdate: i64,
+    #[serde(default)]
+    pub owner: Owner,
+    #[serde(default)]
+    pub pages: Vec<VideoPage>,
+}
+
+/// DASH 音频流
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DashAudio {
+    /// 音质代码（30216/30232/30280 等）
+    pub id: u32,
+    #[serde(alias = "base_url")]
+    pub base_url: String,
+    #[serde(default, alias = "backup_url")]
+    pub backup_url: Option<Vec<String>>,
+    /// 码率（bit/s）
+    pub bandwidth: u64,
+    #[serde(default)]
+    pub codecs: String,
+}
+
+/// DASH 格式播放信息
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Dash {
+    #[serde(default)]
+    pub duration: u64,
+    #[serde(default)]
+    pub audio: Option<Vec<DashAudio>>,
+}
+
+/// 整段（FLV/MP4）播放地址，老视频没有 DASH 时使用
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Durl {
+    pub url: String,
+    #[serde(default)]
+    pub size: u64,
+    /// 时长（毫秒）
+    #[serde(default)]
+    pub length: u64,
+}
+
+/// 播放地址（/x/player/playurl）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PlayUrl {
+    /// 总时长（毫秒）
+    #[serde(default)]
+    pub timelength: u64,
+    #[serde(default)]
+    pub dash: Option<Dash>,
+    #[serde(default)]
+    pub durl: Option<Vec<Durl>>,
+}
+
+impl PlayUrl {
+    /// DASH 音频流（按码率从高到低）
+    pub fn audio_streams(&self) -> Vec<DashAudio> {
+        let mut streams = self.dash
+            .as_ref()
+            .and_then(|dash| dash.audio.clone())
+            .unwrap_or_default();
+        streams.sort_by_key(|audio| std::cmp::Reverse(audio.bandwidth));
+        streams
+    }
+}
+
+/// 视频可用状态
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum VideoState {
+    Ok,
+    Banned,
+    NotFound,
+    Error,
+}
+
+/// 视频状态检查结果
+#[derive(Debug, Serialize, Clone)]
+pub struct VideoStatus {
+    pub status: VideoState,
+    pub message: Option<String>,
+}
+
+/// 请求接口并返回原始响应（路径带 `/wbi/` 的接口自动签名）
+///
+/// 请求前经过风控限速，HTTP 412 视为触发风控；code 由调用方检查
+async fn request(path: &str, query: &[(&str, String)]) -> Result<ApiResponse, ApiError> {
+    let mut query: Vec<(String, String)> = query
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.clone()))
+        .collect();
+    if wbi::needs_signing(path) {
+        query = wbi::sign(&query).await?;
+    }
+    
+    risk_control::acquire().await.map_err(ApiError::cooling_down)?;
+    let client = get_http_client().await?;
+    let response = add_bilibili_headers(client.get(format!("{}{}", BILIBILI_API_BASE, path)))
+        .header("Accept", "application/json, text/plain, */*")
+        .header("Accept-Language", "zh-CN,zh;q=0.9,en;q=0.8")
+        .query(&query)
+        .send()
+        .await
+        .map_err(|e| format!("请求失败: {}", e))?;
+    
+    let status = response.status();
+    let body = response
+        .text()
+        .await
+        .map_err(|e| format!("读取响应失败: {}", e))?;
+    if status.as_u16() == 412 {
+        return Err(ApiError::risk_control(-412));
+    }
+    if !status.is_success() {
+        return Err(format!("HTTP {}", status.as_u16()).into());
+    }
+    serde_json::from_str(&body).map_err(|e| format!("解析响应失败: {}", e).into())
+}
+
+/// 请求接口并按类型解析 data（code 不为 0 时返回错误，可缓存的接口优先使用缓存）
+async fn get<T: DeserializeOwned>(path: &str, query: &[(&str, String)]) -> Result<T, ApiError> {
+    let url = format!("{}{}", BILIBILI_API_BASE, path);
+    let params: Vec<(String, String)> = query
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.clone()))
+        .collect();
+    if let Some(cached) = api_cache::load(&url, &params).await {
+        if let Ok(data) = serde_json::from_value(cached["data"].clone()) {
+            return Ok(data);
+        }
+    }
+    
+    let mut response = request(path, query).await?;
+    // 签名被拒绝时可能是密钥已轮换，重新获取密钥后重试一次，仍为 -352 时才按风控处理
+    if wbi::needs_signing(path) && matches!(response.code, -352 | -403) {
+        wbi::invalidate().await;
+        response = request(path, query).await?;
+    }
+    if risk_control::is_risk_code(response.code) {
+        return Err(ApiError::risk_control(response.code));
+    }
+    if response.code != 0 {
+        return Err(format!("B站接口返回错误 ({}): {}", response.code, response.message).into());
+    }
+    risk_control::report_ok();
+    let data = serde_json::from_value(response.data.clone())
+        .map_err(|e| format!("解析 {} 数据失败: {}", path, e))?;
+    let cached = serde_json::json!({
+        "code": response.code,
+        "message": response.message,
+        "data": response.data,
+    });
+    api_cache::store(&url, &params, &cached).await;
+    Ok(data)
+}
+
+/// 解析时长（"mm:ss"、"hh:mm:ss" 或秒数）
+fn parse_duration(value: &serde_json::Value) -> u64 {
+    if let Some(secs) = value.as_u64() {
+        return secs;
+    }
+    let parts: Vec<u64> = value
+        .as_str()
+        .unwrap_or("")
+        .split(':')
+        .map(|part| part.trim().parse().unwrap_or(0))
+        .collect();
+    match parts.as_slice() {
+        [m, s] => m * 60 + s,
+        [h, m, s] => h * 3600 + m * 60 + s,
+        _ => 0,
+    }
+}
+
+/// 去除搜索结果中的高亮标签
+fn strip_html_tags(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text
+}
+
+/// 补全封面地址协议（协议相对地址和 http 统一为 https）
+fn normalize_image_url(url: &str) -> String {
+    if let Some(rest) = url.strip_prefix("//") {
+        format!("https://{}", rest)
+    } else if let Some(rest) = url.strip_prefix("http://") {
+        format!("https://{}", rest)
+    } else {
+        url.to_string()
+    }
+}
+
+/// 搜索视频
+///
+/// duration 为 B 站的时长筛选：1 为 10 分钟以下，2 为 10-30 分钟，3 为 30-60 分钟，4 为 60 分钟以上
+pub async fn search_videos(
+    keyword: &str,
+    page: u32,
+    duration: Option<u8>,
+    order: SearchOrder,
+) -> Result<SearchPage, ApiError> {
+    let mut query = vec![
+        ("keyword", keyword.to_string()),
+        ("search_type", "video".to_string()),
+        ("order", order.as_str().to_string()),
+        ("page", page.max(1).to_string()),
+        ("page_size", BILIBILI_SEARCH_PAGE_SIZE.to_string()),
+    ];
+    if let Some(duration) = duration {
+        query.push(("duration", duration.to_string()));
+    }
+    
+    let raw: RawSearchPage = get("/x/web-interface/wbi/search/type", &query).await?;
+    Ok(SearchPage {
+        page: raw.page,
+        num_pages: raw.num_pages,
+        num_results: raw.num_results,
+        videos: raw.result
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|item| !item.bvid.is_empty())
+            .map(|item| SearchVideo {
+                title: strip_html_tags(&item.title),
+                pic: normalize_image_url(&item.pic),
+                duration: parse_duration(&item.duration),
+                play: item.play.as_u64().or_else(|| item.play.as_str()?.parse().ok()),
+                bvid: item.bvid,
+                author: item.author,
+                mid: item.mid,
+            })
+            .collect(),
+    })
+}
+
+/// 获取视频详情
+pub async fn get_video_info(bvid: &str) -> Result<VideoInfo, ApiError> {
+    let mut info: VideoInfo = get("/x/web-interface/view", &[("bvid", bvid.to_string())]).await?;
+    info.pic = normalize_image_url(&info.pic);
+    Ok(info)
+}
+
+/// 获取视频的分 P 列表
+pub async fn get_pages(bvid: &str) -> Result<Vec<VideoPage>, ApiError> {
+    get("/x/player/pagelist", &[("bvid", bvid.to_string())]).await
+}
+
+/// 获取播放地址（fnval=16 请求 DASH 格式，音视频分离，支持音质选择）
+pub async fn get_play_url(bvid: &str, cid: u64) -> Result<PlayUrl, ApiError> {
+    get("/x/player/wbi/playurl", &[
+        ("bvid", bvid.to_string()),
+        ("cid", cid.to_string()),
+        ("fnval", "16".to_string()),
+    ])
+    .await
+}
+
+/// 检查视频是否仍可播放
+pub async fn check_video_status(bvid: &str) -> VideoStatus {
+    let (status, message) = match request("/x/web-interface/view", &[("bvid", bvid.to_string())]).await {
+        Ok(response) if risk_control::is_risk_code(response.code) => {
+            let error = ApiError::risk_control(response.code);
+            (VideoState::Error, Some(error.to_string()))
+        }
+        Ok(response) => match response.code {
+            0 => {
+                risk_control::report_ok();
+                (VideoState::Ok, None)
+            }
+            -404 => (VideoState::NotFound, Some("视频不存在".to_string())),
+            62002 | 62004 => (VideoState::Banned, Some("视频已被下架".to_string())),
+            -403 => (VideoState::Banned, Some("视频无法访问".to_string())),
+            code => (VideoState::Error, Some(format!("视频状态异常 ({})", code))),
+        },
+        Err(error @ ApiError::RiskControl { .. }) => (VideoState::Error, Some(error.to_string())),
+        Err(_) => (VideoState::Error, Some("网络错误".to_string())),
+    };
+    VideoStatus { status, message }
+}
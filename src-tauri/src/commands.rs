@@ -4,7 +4,22 @@
 
 use crate::constants::{file_ext, INVALID_FILENAME_CHARS};
 use crate::http_client::{add_bilibili_headers, get_http_client};
+use crate::access_log;
+use crate::api_cache;
+use crate::bilibili;
+use crate::dlna;
+use crate::dlna_renderer;
+use crate::lan;
+use crate::library;
+use crate::login;
+use crate::pick_filter;
+use crate::playlist::{self, PlaylistTrack};
 use crate::proxy;
+use crate::proxy_config;
+use crate::random_pick;
+use crate::risk_control;
+use crate::stations;
+use crate::wbi;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::io::Write;
@@ -205,6 +220,137 @@ pub async fn install_update() -> Result<(), String> {
     Err("更新功能暂未实现".to_string())
 }
 
+/// 搜索视频（duration 为 B 站时长筛选 1-4，不传则不限制；order 默认综合排序）
+#[tauri::command]
+pub async fn bilibili_search(
+    keyword: String,
+    page: Option<u32>,
+    duration: Option<u8>,
+    order: Option<bilibili::SearchOrder>,
+) -> Result<bilibili::SearchPage, bilibili::ApiError> {
+    bilibili::search_videos(&keyword, page.unwrap_or(1), duration, order.unwrap_or_default()).await
+}
+
+/// 获取视频详情
+#[tauri::command]
+pub async fn bilibili_video_info(bvid: String) -> Result<bilibili::VideoInfo, bilibili::ApiError> {
+    bilibili::get_video_info(&bvid).await
+}
+
+/// 获取播放地址
+#[tauri::command]
+pub async fn bilibili_play_url(bvid: String, cid: u64) -> Result<bilibili::PlayUrl, bilibili::ApiError> {
+    bilibili::get_play_url(&bvid, cid).await
+}
+
+/// 检查视频是否仍可播放
+#[tauri::command]
+pub async fn bilibili_video_status(bvid: String) -> Result<bilibili::VideoStatus, String> {
+    Ok(bilibili::check_video_status(&bvid).await)
+}
+
+/// 随机选取一个相声视频（gang_type 为 dan/dui/all，指定 station 时使用该电台）
+#[tauri::command]
+pub async fn random_pick(
+    gang_type: random_pick::GangType,
+    station: Option<String>,
+    custom_keywords: Option<Vec<String>>,
+    quality: Option<String>,
+) -> Result<random_pick::PickedItem, bilibili::ApiError> {
+    random_pick::pick(
+        gang_type,
+        station.as_deref(),
+        &custom_keywords.unwrap_or_default(),
+        quality.as_deref(),
+    )
+    .await
+}
+
+/// 清空随机选取使用的播放历史
+#[tauri::command]
+pub async fn clear_played_history() -> Result<(), String> {
+    random_pick::clear_history()
+}
+
+/// 获取随机选取的筛选条件
+#[tauri::command]
+pub async fn get_pick_filter() -> Result<pick_filter::PickFilter, String> {
+    Ok(pick_filter::get())
+}
+
+/// 更新随机选取的筛选条件（时长范围、排除关键词、最低播放量）
+#[tauri::command]
+pub async fn set_pick_filter(filter: pick_filter::PickFilter) -> Result<pick_filter::PickFilter, String> {
+    pick_filter::set(filter)
+}
+
+/// 获取电台列表和当前选择的电台
+#[tauri::command]
+pub async fn list_stations() -> Result<stations::StationList, String> {
+    Ok(stations::list())
+}
+
+/// 选择“纲一下”使用的电台（不传时取消选择）
+#[tauri::command]
+pub async fn select_station(id: Option<String>) -> Result<stations::StationList, String> {
+    stations::select(id)
+}
+
+/// 重新读取电台配置文件
+#[tauri::command]
+pub async fn reload_stations() -> Result<stations::StationList, String> {
+    stations::reload()
+}
+
+/// 获取风控状态（是否在冷却中、剩余秒数）
+#[tauri::command]
+pub async fn get_risk_control_status() -> Result<risk_control::RiskControlStatus, String> {
+    Ok(risk_control::status())
+}
+
+/// 设置触发风控后是否重新获取 buvid 和 bili_ticket
+#[tauri::command]
+pub async fn set_risk_control_rewarm(enabled: bool) -> Result<risk_control::RiskControlStatus, String> {
+    Ok(risk_control::set_rewarm_enabled(enabled))
+}
+
+/// 申请扫码登录二维码（format 为 "png" 或 "svg"，默认 svg）
+#[tauri::command]
+pub async fn generate_login_qr(format: Option<String>) -> Result<login::QrLogin, String> {
+    login::generate_qr(format.as_deref()).await
+}
+
+/// 查询扫码登录状态（登录成功时保存 Cookie）
+#[tauri::command]
+pub async fn poll_login_qr(qrcode_key: String) -> Result<login::QrLoginState, String> {
+    login::poll_qr(&qrcode_key).await
+}
+
+/// 获取登录状态
+#[tauri::command]
+pub async fn get_login_status() -> Result<login::LoginStatus, String> {
+    login::get_login_status().await
+}
+
+/// 退出登录
+#[tauri::command]
+pub async fn logout() -> Result<(), String> {
+    login::logout().await
+}
+
+/// 清空保存的 Cookie（同时会退出登录）
+#[tauri::command]
+pub async fn clear_cookies() -> Result<(), String> {
+    crate::http_client::clear_cookies().await;
+    Ok(())
+}
+
+/// 清空接口响应缓存
+#[tauri::command]
+pub async fn clear_api_cache() -> Result<(), String> {
+    api_cache::clear()
+}
+
 /// HTTP 代理请求（用于绕过 CORS）
 #[tauri::command]
 pub async fn http_request(
@@ -238,24 +384,92 @@ pub async fn http_request(
         }
     }
     
-    // 添加查询参数（将 JSON Value 转换为字符串 HashMap）
-    if let Some(query_params_json) = params {
-        let mut query_map = std::collections::HashMap::new();
-        if let Some(obj) = query_params_json.as_object() {
-            for (key, value) in obj {
-                let str_value = match value {
-                    serde_json::Value::String(s) => s.clone(),
-                    serde_json::Value::Number(n) => n.to_string(),
-                    serde_json::Value::Bool(b) => b.to_string(),
-                    serde_json::Value::Null => String::new(),
-                    _ => value.to_string(),
-                };
-                query_map.insert(key.clone(), str_value);
-            }
+    // 添加查询参数（将 JSON Value 转换为字符串键值对）
+    let mut query_pairs: Vec<(String, String)> = Vec::new();
+    if let Some(obj) = params.as_ref().and_then(|p| p.as_object()) {
+        for (key, value) in obj {
+            let str_value = match value {
+                serde_json::Value::String(s) => s.clone(),
+                serde_json::Value::Number(n) => n.to_string(),
+                serde_json::Value::Bool(b) => b.to_string(),
+                serde_json::Value::Null => String::new(),
+                _ => value.to_string(),
+            };
+            query_pairs.push((key.clone(), str_value));
         }
-        request = request.query(&query_map);
     }
     
+    // 可缓存的接口（视频详情、搜索）命中缓存时直接返回
+    if method == "GET" {
+        if let Some(cached) = api_cache::load(&url, &query_pairs).await {
+            return Ok(serde_json::json!({
+                "status": 200,
+                "headers": {},
+                "data": cached
+            }));
+        }
+    }
+    // 路径带 /wbi/ 的接口需要 WBI 签名
+    let needs_signing = reqwest::Url::parse(&url)
+        .map(|u| wbi::needs_signing(u.path()))
+        .unwrap_or(false);
+    let mut response = send_json(with_query(&request, &query_pairs, needs_signing).await?).await?;
+    // 签名被拒绝时可能是密钥已轮换，重新获取密钥后重试一次，仍为 -352 时才按风控处理
+    if needs_signing && matches!(response.2["code"].as_i64(), Some(-352 | -403)) {
+        wbi::invalidate().await;
+        response = send_json(with_query(&request, &query_pairs, needs_signing).await?).await?;
+    }
+    let (status, headers_map, json_value) = response;
+    
+    // 触发风控时进入全局冷却，并以错误返回，避免前端当作空结果处理
+    let risk_code = if status.as_u16() == 412 {
+        Some(-412)
+    } else {
+        json_value["code"].as_i64().filter(|code| risk_control::is_risk_code(*code))
+    };
+    if let Some(code) = risk_code {
+        let secs = risk_control::report_blocked(code);
+        return Err(format!("请求过于频繁，已被B站风控 ({})，请 {} 秒后重试", code, secs));
+    }
+    if json_value["code"].as_i64() == Some(0) {
+        risk_control::report_ok();
+    }
+    if method == "GET" && status.is_success() {
+        api_cache::store(&url, &query_pairs, &json_value).await;
+    }
+    
+    Ok(serde_json::json!({
+        "status": status.as_u16(),
+        "headers": headers_map,
+        "data": json_value
+    }))
+}
+
+/// 复制请求并附加查询参数（需要时重新签名，每次发送都使用新的 wts）
+async fn with_query(
+    request: &reqwest::RequestBuilder,
+    query_pairs: &[(String, String)],
+    needs_signing: bool,
+) -> Result<reqwest::RequestBuilder, String> {
+    let request = request.try_clone().ok_or("无法复制请求")?;
+    let query = if needs_signing {
+        wbi::sign(query_pairs).await?
+    } else {
+        query_pairs.to_vec()
+    };
+    if query.is_empty() {
+        return Ok(request);
+    }
+    Ok(request.query(&query))
+}
+
+/// 经过风控限速后发送请求，返回状态码、响应头和响应体（不是 JSON 时包装为 { text }）
+async fn send_json(
+    request: reqwest::RequestBuilder,
+) -> Result<(reqwest::StatusCode, std::collections::HashMap<String, String>, serde_json::Value), String> {
+    risk_control::acquire()
+        .await
+        .map_err(|secs| format!("B站风控冷却中，请 {} 秒后重试", secs))?;
     let response = request
         .send()
         .await
@@ -280,12 +494,7 @@ pub async fn http_request(
     let json_value = serde_json::from_str(&body).unwrap_or_else(|_| {
         serde_json::json!({ "text": body })
     });
-    
-    Ok(serde_json::json!({
-        "status": status.as_u16(),
-        "headers": headers_map,
-        "data": json_value
-    }))
+    Ok((status, headers_map, json_value))
 }
 
 /// 启动代理服务器
@@ -294,12 +503,229 @@ pub async fn start_proxy_server() -> Result<u16, String> {
     proxy::start_proxy_server().await
 }
 
+/// 停止代理服务器
+#[tauri::command]
+pub async fn stop_proxy_server() -> Result<(), String> {
+    proxy::stop_proxy_server().await
+}
+
+/// 重启代理服务器
+#[tauri::command]
+pub async fn restart_proxy_server() -> Result<u16, String> {
+    proxy::restart_proxy_server().await
+}
+
+/// 获取代理服务器状态
+#[tauri::command]
+pub async fn proxy_status() -> Result<proxy::ProxyStatus, String> {
+    Ok(proxy::proxy_status().await)
+}
+
+/// 获取代理端口和地址设置
+#[tauri::command]
+pub async fn get_proxy_config() -> Result<proxy_config::ProxyConfig, String> {
+    Ok(proxy_config::get())
+}
+
+/// 更新代理端口和地址设置（代理正在运行时按新设置重启）
+#[tauri::command]
+pub async fn set_proxy_config(
+    preferred_port: Option<u16>,
+    fixed_port: bool,
+    ipv6: bool,
+) -> Result<proxy_config::ProxyConfig, String> {
+    proxy::set_proxy_config(preferred_port, fixed_port, ipv6).await
+}
+
+/// 获取代理运行指标（诊断面板使用）
+#[tauri::command]
+pub async fn proxy_metrics() -> Result<crate::metrics::MetricsSnapshot, String> {
+    Ok(proxy::proxy_metrics().await)
+}
+
+/// 查询代理访问日志（按时间从新到旧，用于排查播放卡顿）
+#[tauri::command]
+pub async fn query_proxy_access_log(
+    query: Option<access_log::AccessLogQuery>,
+) -> Result<Vec<access_log::AccessLogEntry>, String> {
+    access_log::query(query.unwrap_or_default()).await
+}
+
+/// 清空代理访问日志
+#[tauri::command]
+pub async fn clear_proxy_access_log() -> Result<(), String> {
+    access_log::clear()
+}
+
 /// 代理音频文件
 #[tauri::command]
 pub async fn proxy_audio(url: String) -> Result<String, String> {
     proxy::proxy_audio(url).await
 }
 
+/// 代理封面图片
+#[tauri::command]
+pub async fn proxy_image(
+    url: String,
+    width: Option<u32>,
+    height: Option<u32>,
+) -> Result<String, String> {
+    proxy::proxy_image(url, width, height).await
+}
+
+/// 预取下一首音频（传入音频地址，或 bvid 和 cid 由后端解析）
+#[tauri::command]
+pub async fn prefetch_audio(
+    url: Option<String>,
+    bvid: Option<String>,
+    cid: Option<u64>,
+) -> Result<(), String> {
+    proxy::prefetch_audio(url, bvid, cid).await
+}
+
+/// 获取按 bvid 和 cid 播放的代理地址（网速不足时后续播放自动降低码率）
+#[tauri::command]
+pub async fn proxy_play_url(
+    bvid: String,
+    cid: u64,
+    quality: Option<String>,
+) -> Result<String, String> {
+    proxy::proxy_play_url(bvid, cid, quality).await
+}
+
+/// 获取代理提供的 M3U8 播放列表地址（供外部播放器打开）
+#[tauri::command]
+pub async fn proxy_playlist_url(tracks: Vec<PlaylistTrack>) -> Result<String, String> {
+    proxy::proxy_playlist_url(tracks).await
+}
+
+/// 把播放列表导出为 M3U8 文件
+#[tauri::command]
+pub async fn export_playlist(tracks: Vec<PlaylistTrack>, path: String) -> Result<String, String> {
+    proxy::export_playlist(tracks, path).await
+}
+
+/// 开启局域网播放（address 为绑定的本机地址，不传时自动检测）
+#[tauri::command]
+pub async fn enable_lan_streaming(address: Option<String>) -> Result<lan::LanStatus, String> {
+    lan::enable(address).await
+}
+
+/// 关闭局域网播放
+#[tauri::command]
+pub async fn disable_lan_streaming() -> Result<(), String> {
+    lan::disable().await
+}
+
+/// 获取局域网播放状态（含播放页地址）
+#[tauri::command]
+pub async fn lan_streaming_status() -> Result<lan::LanStatus, String> {
+    Ok(lan::status().await)
+}
+
+/// 重新生成局域网访问令牌（返回使用新令牌的地址）
+#[tauri::command]
+pub async fn regenerate_lan_token() -> Result<lan::LanStatus, String> {
+    lan::regenerate_token().await
+}
+
+/// 重新生成播放列表访问令牌（已导出的播放列表需要重新导出）
+#[tauri::command]
+pub async fn regenerate_playlist_token() -> Result<(), String> {
+    playlist::regenerate_token()
+}
+
+/// 更新局域网播放页中的当前曲目
+#[tauri::command]
+pub async fn set_lan_now_playing(track: lan::LanTrack) -> Result<(), String> {
+    lan::set_now_playing(track);
+    Ok(())
+}
+
+/// 开启 DLNA 媒体服务（局域网播放未开启时自动开启）
+#[tauri::command]
+pub async fn enable_dlna() -> Result<dlna::DlnaStatus, String> {
+    dlna::enable().await
+}
+
+/// 关闭 DLNA 媒体服务
+#[tauri::command]
+pub async fn disable_dlna() -> Result<(), String> {
+    dlna::disable().await
+}
+
+/// 获取 DLNA 媒体服务状态
+#[tauri::command]
+pub async fn dlna_status() -> Result<dlna::DlnaStatus, String> {
+    Ok(dlna::status().await)
+}
+
+/// 同步媒体库（收藏列表和下载目录，供 DLNA 和播客订阅使用）
+#[tauri::command]
+pub async fn set_media_library(
+    favorites: Vec<lan::LanTrack>,
+    download_dir: Option<String>,
+) -> Result<(), String> {
+    library::set_library(favorites, download_dir);
+    Ok(())
+}
+
+/// 搜索局域网中的 DLNA 播放设备
+#[tauri::command]
+pub async fn discover_renderers(
+    timeout_ms: Option<u64>,
+) -> Result<Vec<dlna_renderer::RendererInfo>, String> {
+    dlna_renderer::discover(timeout_ms).await
+}
+
+/// 通过设备描述地址添加播放设备
+#[tauri::command]
+pub async fn connect_renderer(location: String) -> Result<dlna_renderer::RendererInfo, String> {
+    dlna_renderer::connect(location).await
+}
+
+/// 在播放设备上播放曲目
+#[tauri::command]
+pub async fn renderer_play(renderer_id: String, track: lan::LanTrack) -> Result<(), String> {
+    dlna_renderer::play(&renderer_id, track).await
+}
+
+/// 暂停播放设备
+#[tauri::command]
+pub async fn renderer_pause(renderer_id: String) -> Result<(), String> {
+    dlna_renderer::pause(&renderer_id).await
+}
+
+/// 播放设备继续播放
+#[tauri::command]
+pub async fn renderer_resume(renderer_id: String) -> Result<(), String> {
+    dlna_renderer::resume(&renderer_id).await
+}
+
+/// 停止播放设备
+#[tauri::command]
+pub async fn renderer_stop(renderer_id: String) -> Result<(), String> {
+    dlna_renderer::stop(&renderer_id).await
+}
+
+/// 播放设备跳转到指定位置（秒）
+#[tauri::command]
+pub async fn renderer_seek(renderer_id: String, position_secs: u64) -> Result<(), String> {
+    dlna_renderer::seek(&renderer_id, position_secs).await
+}
+
+/// 获取代理允许的上游主机后缀
+#[tauri::command]
+pub async fn get_proxy_allowed_hosts() -> Result<Vec<String>, String> {
+    Ok(proxy::get_allowed_hosts())
+}
+
+/// 设置代理允许的上游主机后缀（传空列表恢复默认）
+#[tauri::command]
+pub async fn set_proxy_allowed_hosts(hosts: Vec<String>) -> Result<Vec<String>, String> {
+    proxy::set_allowed_hosts(hosts)
+}
+
 use std::sync::Mutex;
 
 /// 关闭行为状态
@@ -6,8 +6,148 @@
 pub const USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
 pub const BILIBILI_REFERER: &str = "https://www.bilibili.com";
 pub const BILIBILI_ORIGIN: &str = "https://www.bilibili.com";
-pub const PROXY_PORT_RANGE_START: u16 = 8000;
-pub const PROXY_PORT_RANGE_END: u16 = 9000;
+pub const BILIBILI_API_BASE: &str = "https://api.bilibili.com";
+pub const BILIBILI_PASSPORT_BASE: &str = "https://passport.bilibili.com";
+/// 搜索接口每页结果数
+pub const BILIBILI_SEARCH_PAGE_SIZE: u32 = 20;
+/// WBI 签名密钥的缓存时间（秒），密钥每天轮换
+pub const WBI_KEYS_TTL_SECS: u64 = 6 * 60 * 60;
+/// 登录二维码每个模块的像素数
+pub const QR_MODULE_PIXELS: u32 = 8;
+/// buvid3/buvid4 Cookie 的有效期（秒）
+pub const BUVID_MAX_AGE_SECS: u64 = 365 * 24 * 60 * 60;
+/// 生成 bili_ticket 使用的密钥编号与 HMAC 密钥
+pub const BILI_TICKET_KEY_ID: &str = "ec02";
+pub const BILI_TICKET_HMAC_KEY: &str = "XgwSnGZ1p";
+/// bili_ticket 剩余有效期少于该值时重新生成（秒）
+pub const BILI_TICKET_REFRESH_MARGIN_SECS: u64 = 24 * 60 * 60;
+/// 检查 bili_ticket 是否需要更新的间隔（秒）
+pub const BILI_TICKET_CHECK_INTERVAL_SECS: u64 = 60 * 60;
+/// 触发风控后的冷却时间（秒），连续触发时加倍，不超过上限
+pub const RISK_COOLDOWN_BASE_SECS: u64 = 30;
+pub const RISK_COOLDOWN_MAX_SECS: u64 = 10 * 60;
+/// B 站接口请求的令牌桶容量与每秒补充的令牌数
+pub const RISK_BUCKET_CAPACITY: f64 = 4.0;
+pub const RISK_BUCKET_REFILL_PER_SEC: f64 = 1.0;
+/// 随机选取的默认关键词（未设置自定义关键词时使用）
+pub const DEFAULT_KEYWORDS: &[&str] = &[
+    "郭德纲 于谦 相声",
+    "德云社 相声",
+    "郭德纲 相声 完整版",
+    "于谦 郭德纲",
+    "德云社 郭德纲",
+    "郭德纲 单口相声",
+    "郭德纲 经典相声",
+];
+/// 单口相声关键词
+pub const DANKOU_KEYWORDS: &[&str] = &[
+    "郭德纲 单口相声",
+    "郭德纲 单口",
+    "郭德纲 德云社 单口相声",
+    "郭德纲 评书",
+    "郭德纲 单口相声 完整版",
+];
+/// 对口相声关键词
+pub const DUIKOU_KEYWORDS: &[&str] = &[
+    "郭德纲 于谦 相声",
+    "郭德纲 德云社 对口相声",
+    "郭德纲 于谦 对口",
+    "于谦 郭德纲 相声",
+    "郭德纲 对口相声 完整版",
+];
+/// 随机选取时搜索的最大页码
+pub const RANDOM_PICK_MAX_PAGE: u32 = 3;
+/// 随机选取时优先使用的 B 站时长筛选（30-60 分钟）
+pub const RANDOM_PICK_PREFERRED_DURATION: u8 = 3;
+/// 随机选取的默认时长范围（秒）：排除短片段和超长合辑
+pub const PICK_FILTER_MIN_DURATION_SECS: u64 = 5 * 60;
+pub const PICK_FILTER_MAX_DURATION_SECS: u64 = 3 * 60 * 60;
+/// 随机选取时默认排除的标题关键词
+pub const PICK_FILTER_EXCLUDE_KEYWORDS: &[&str] = &["reaction", "解说", "合集混剪"];
+/// 保存的播放历史条数（选取时排除这些视频）
+pub const PLAYED_HISTORY_SIZE: usize = 100;
+/// 接口响应缓存时间（秒）：视频详情 1 天，搜索 10 分钟
+pub const API_CACHE_VIEW_TTL_SECS: u64 = 24 * 60 * 60;
+pub const API_CACHE_SEARCH_TTL_SECS: u64 = 10 * 60;
+/// 接口响应磁盘缓存最多保留的文件数
+pub const API_CACHE_MAX_FILES: usize = 2000;
+/// 代理默认优先使用的端口（避开常见开发服务器端口，被占用时由系统分配）
+pub const PROXY_DEFAULT_PORT: u16 = 47810;
+/// 停止代理时等待现有连接结束的最长时间（秒）
+pub const PROXY_SHUTDOWN_TIMEOUT_SECS: u64 = 3;
+/// 代理访问令牌长度
+pub const PROXY_TOKEN_LENGTH: usize = 32;
+/// 代理支持的 HTTP 方法
+pub const PROXY_ALLOWED_METHODS: &str = "GET, HEAD, OPTIONS";
+/// 代理访问上游时最多跟随的重定向次数
+pub const PROXY_MAX_REDIRECTS: usize = 10;
+/// 代理 CORS 预检结果缓存时间（秒）
+pub const PROXY_CORS_MAX_AGE_SECS: u64 = 86400;
+/// 代理默认允许的上游主机后缀（B 站音视频 CDN 与图片域名）
+pub const PROXY_DEFAULT_ALLOWED_HOSTS: &[&str] = &[
+    "bilivideo.com",
+    "bilivideo.cn",
+    "akamaized.net",
+    "hdslb.com",
+    "biliimg.com",
+    "bilibili.com",
+];
+/// 预取音频开头的字节数
+pub const AUDIO_PREFETCH_BYTES: u64 = 2 * 1024 * 1024;
+/// 预取缓存最多保留的曲目数
+pub const AUDIO_PREFETCH_CACHE_CAPACITY: usize = 3;
+/// 预取开始前的等待时间（毫秒），让当前曲目的首次缓冲优先
+pub const AUDIO_PREFETCH_DELAY_MS: u64 = 1500;
+/// 合并请求时每个上游传输最多缓冲的字节数（超过后等待最慢的客户端）
+pub const INFLIGHT_BUFFER_BYTES: u64 = 4 * 1024 * 1024;
+/// 新请求起点最多可领先已接收位置的字节数（超过则单独请求上游）
+pub const INFLIGHT_JOIN_LAG_BYTES: u64 = 512 * 1024;
+/// 封面图片缩放的最大边长
+pub const IMAGE_MAX_DIMENSION: u32 = 2048;
+/// 封面图片重新编码的 JPEG 质量
+pub const IMAGE_JPEG_QUALITY: u8 = 85;
+/// 封面图片原图最大字节数
+pub const IMAGE_MAX_BYTES: usize = 10 * 1024 * 1024;
+/// 封面图片磁盘缓存最多保留的文件数
+pub const IMAGE_CACHE_MAX_FILES: usize = 500;
+/// 封面图片响应的缓存时间（秒）
+pub const IMAGE_CACHE_MAX_AGE_SECS: u64 = 365 * 24 * 60 * 60;
+/// 吞吐量采样长度（每个上游请求统计开头这么多字节的传输速度）
+pub const THROUGHPUT_SAMPLE_BYTES: u64 = 256 * 1024;
+/// 吞吐量滑动平均系数
+pub const THROUGHPUT_EWMA_ALPHA: f64 = 0.3;
+/// 降低码率的余量（吞吐量低于码率的这个倍数时切换到更低码率）
+pub const QUALITY_DOWNGRADE_MARGIN: f64 = 1.5;
+/// 播放会话的地址有效期（B 站音频地址约 2 小时过期，提前重新解析）
+pub const PLAY_SESSION_TTL_SECS: u64 = 90 * 60;
+/// 最多保留的播放会话数
+pub const PLAY_SESSION_CAPACITY: usize = 8;
+/// 局域网播放优先使用的端口（被占用时由系统分配）
+pub const LAN_STREAMING_PORT: u16 = 8910;
+/// 局域网播放页显示的最近曲目数
+pub const LAN_RECENT_TRACKS: usize = 20;
+/// SSDP 组播地址
+pub const SSDP_MULTICAST_ADDR: &str = "239.255.255.250";
+/// SSDP 端口
+pub const SSDP_PORT: u16 = 1900;
+/// DLNA 设备通告的有效期（秒）
+pub const DLNA_MAX_AGE_SECS: u64 = 1800;
+/// DLNA 设备重新通告的间隔（秒）
+pub const DLNA_NOTIFY_INTERVAL_SECS: u64 = 600;
+/// 搜索 DLNA 播放设备的默认等待时间（毫秒）
+pub const DLNA_DISCOVERY_TIMEOUT_MS: u64 = 3000;
+/// 请求 DLNA 播放设备的超时时间（秒）
+pub const DLNA_REQUEST_TIMEOUT_SECS: u64 = 5;
+/// DLNA 设备显示名称
+pub const DLNA_FRIENDLY_NAME: &str = "纲一下";
+/// 代理访问日志文件名
+pub const ACCESS_LOG_FILE_NAME: &str = "proxy-access.log";
+/// 单个访问日志文件的最大大小（超过后轮转）
+pub const ACCESS_LOG_MAX_BYTES: u64 = 2 * 1024 * 1024;
+/// 保留的历史访问日志文件数
+pub const ACCESS_LOG_MAX_FILES: usize = 3;
+/// 查询访问日志时默认返回的条数
+pub const ACCESS_LOG_QUERY_LIMIT: usize = 200;
 
 /// 文件扩展名
 pub mod file_ext {
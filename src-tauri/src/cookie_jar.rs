@@ -0,0 +1,173 @@
+//! Cookie 存储模块
+//! 
+//! 为全局 HTTP 客户端提供持久化的 Cookie 存储：启动时从应用数据目录加载（跳过已过期的），
+//! 收到新的 Cookie 后在后台写回磁盘。只保存带有效期的 Cookie，会话 Cookie 仅保留在内存中
+
+use crate::paths;
+use reqwest::header::HeaderValue;
+use std::fs;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+
+/// 等待写入磁盘的操作（只保留最新的一次）
+enum PendingWrite {
+    Save(Vec<u8>),
+    Remove,
+}
+
+/// 后台写入状态
+#[derive(Default)]
+struct WriterState {
+    pending: Option<PendingWrite>,
+    /// 是否已有写入任务在运行
+    running: bool,
+}
+
+/// 持久化的 Cookie 存储
+pub struct CookieJar {
+    store: RwLock<cookie_store::CookieStore>,
+    /// 保存路径（应用目录未初始化时为 None，只在内存中保存）
+    path: Option<PathBuf>,
+    writer: Arc<Mutex<WriterState>>,
+}
+
+impl CookieJar {
+    /// 从磁盘加载 Cookie（文件不存在或损坏时使用空存储）
+    pub fn load() -> Self {
+        let path = paths::data_file("cookies.json").ok();
+        let store = path
+            .as_ref()
+            .and_then(|path| fs::File::open(path).ok())
+            .and_then(|file| cookie_store::CookieStore::load_json(BufReader::new(file)).ok())
+            .unwrap_or_default();
+        CookieJar {
+            store: RwLock::new(store),
+            path,
+            writer: Arc::default(),
+        }
+    }
+    
+    /// 写回磁盘（只保存未过期且带有效期的 Cookie）
+    fn save(&self) {
+        if self.path.is_none() {
+            return;
+        }
+        let mut data = Vec::new();
+        match self.store.read() {
+            Ok(store) if store.save_json(&mut data).is_ok() => {}
+            _ => return,
+        }
+        self.schedule(PendingWrite::Save(data));
+    }
+    
+    /// 安排写入磁盘：同一时间只有一个写入任务，期间的多次修改合并为最后一次
+    ///
+    /// 在 tokio 工作线程上调用时（HTTP 客户端收到 Set-Cookie）转到阻塞线程池执行
+    fn schedule(&self, write: PendingWrite) {
+        let Some(path) = self.path.clone() else {
+            return;
+        };
+        {
+            let Ok(mut writer) = self.writer.lock() else {
+                return;
+            };
+            writer.pending = Some(write);
+            if writer.running {
+                return;
+            }
+            writer.running = true;
+        }
+        
+        let writer = self.writer.clone();
+        let task = move || flush(&writer, &path);
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn_blocking(task);
+            }
+            Err(_) => task(),
+        }
+    }
+    
+    /// 添加一条 Set-Cookie 格式的 Cookie
+    pub fn add_cookie_str(&self, cookie: &str, url: &reqwest::Url) {
+        if let Ok(mut store) = self.store.write() {
+            let _ = store.parse(cookie, url);
+        }
+        self.save();
+    }
+    
+    /// 读取发送给 url 的 Cookie 值
+    pub fn value(&self, url: &reqwest::Url, name: &str) -> Option<String> {
+        let store = self.store.read().ok()?;
+        let value = store
+            .get_request_values(url)
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value.to_string());
+        value
+    }
+    
+    /// 清空所有 Cookie 并删除磁盘文件
+    pub fn clear(&self) {
+        if let Ok(mut store) = self.store.write() {
+            store.clear();
+        }
+        // 与保存使用同一个写入队列，避免排在前面的保存重新写出文件
+        self.schedule(PendingWrite::Remove);
+    }
+}
+
+/// 依次执行等待中的写入，直到没有新的修改
+fn flush(writer: &Mutex<WriterState>, path: &Path) {
+    loop {
+        let write = match writer.lock() {
+            Ok(mut writer) => match writer.pending.take() {
+                Some(write) => write,
+                None => {
+                    writer.running = false;
+                    return;
+                }
+            },
+            Err(_) => return,
+        };
+        let result = match write {
+            PendingWrite::Save(data) => paths::write_atomic(path, &data),
+            PendingWrite::Remove => match fs::remove_file(path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.to_string()),
+                _ => Ok(()),
+            },
+        };
+        if let Err(e) = result {
+            eprintln!("[Cookie] 保存 Cookie 失败: {}", e);
+        }
+    }
+}
+
+impl reqwest::cookie::CookieStore for CookieJar {
+    fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &reqwest::Url) {
+        let mut changed = false;
+        if let Ok(mut store) = self.store.write() {
+            for header in cookie_headers {
+                if let Ok(cookie) = header.to_str() {
+                    changed |= store.parse(cookie, url).is_ok();
+                }
+            }
+        }
+        if changed {
+            self.save();
+        }
+    }
+    
+    fn cookies(&self, url: &reqwest::Url) -> Option<HeaderValue> {
+        let store = self.store.read().ok()?;
+        let header = store
+            .get_request_values(url)
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+            .join("; ");
+        if header.is_empty() {
+            return None;
+        }
+        HeaderValue::from_str(&header).ok()
+    }
+}
@@ -0,0 +1,994 @@
+//! DLNA 媒体服务模块
+//! 
+//! 在局域网播放服务上提供 UPnP MediaServer：通过 SSDP 通告设备，
+//! 用 ContentDirectory 服务列出媒体库中的收藏和已下载的音频，供电视、音箱等设备浏览播放
+//! 
+//! SSDP 通告会把描述文件地址发送给同一网络中的所有设备，因此描述文件、控制接口和媒体库中的播放地址
+//! 使用单独的 DLNA 令牌，只能浏览和播放媒体库，不会通告局域网令牌
+
+use crate::constants::{
+    DLNA_FRIENDLY_NAME, DLNA_MAX_AGE_SECS, DLNA_NOTIFY_INTERVAL_SECS, SSDP_MULTICAST_ADDR,
+    SSDP_PORT,
+};
+use crate::lan::{self, escape_html};
+use crate::library;
+use crate::paths;
+use crate::persisted_token::PersistedToken;
+use crate::proxy;
+use axum::{
+    body::Body,
+    extract::Path,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{any, get, post},
+    Router,
+};
+use lazy_static::lazy_static;
+use serde::Serialize;
+use socket2::{Domain, Protocol, Socket, Type};
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::sync::RwLock;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::sync::{oneshot, Mutex};
+use tokio::task::JoinHandle;
+
+const MEDIA_SERVER_TYPE: &str = "urn:schemas-upnp-org:device:MediaServer:1";
+const CONTENT_DIRECTORY_TYPE: &str = "urn:schemas-upnp-org:service:ContentDirectory:1";
+const CONNECTION_MANAGER_TYPE: &str = "urn:schemas-upnp-org:service:ConnectionManager:1";
+
+/// 运行中的 SSDP 通告任务
+struct DlnaHandle {
+    description_url: String,
+    shutdown_tx: oneshot::Sender<()>,
+    task: JoinHandle<()>,
+}
+
+/// DLNA 媒体服务状态
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DlnaStatus {
+    pub enabled: bool,
+    pub friendly_name: String,
+    pub description_url: Option<String>,
+}
+
+lazy_static! {
+    static ref DLNA_SERVER: Mutex<Option<DlnaHandle>> = Mutex::new(None);
+    /// 设备 UUID（持久化保存，播放设备不会把重启后的应用识别为新设备）
+    static ref DEVICE_UUID: RwLock<Option<String>> = RwLock::new(None);
+    /// DLNA 访问令牌（随 SSDP 通告公开，只用于 `/dlna` 路由）
+    static ref DLNA_TOKEN: PersistedToken = PersistedToken::new("dlna_token");
+}
+
+/// 获取设备 UUID（首次使用时生成并保存）
+fn device_uuid() -> Result<String, String> {
+    if let Some(uuid) = DEVICE_UUID.read().ok().and_then(|u| u.clone()) {
+        return Ok(uuid);
+    }
+    
+    let path = paths::data_file("dlna_uuid")?;
+    let uuid = match fs::read_to_string(&path) {
+        Ok(uuid) if !uuid.trim().is_empty() => uuid.trim().to_string(),
+        _ => {
+            let bytes: [u8; 16] = rand::random();
+            let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+            let uuid = format!(
+                "{}-{}-{}-{}-{}",
+                &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32]
+            );
+            fs::write(&path, &uuid).map_err(|e| format!("保存设备 UUID 失败: {}", e))?;
+            uuid
+        }
+    };
+    if let Ok(mut cached) = DEVICE_UUID.write() {
+        *cached = Some(uuid.clone());
+    }
+    Ok(uuid)
+}
+
+/// 开启 DLNA 媒体服务（局域网播放未开启时自动开启）
+pub async fn enable() -> Result<DlnaStatus, String> {
+    let mut server_guard = DLNA_SERVER.lock().await;
+    if let Some(server) = server_guard.as_ref() {
+        if !server.task.is_finished() {
+            drop(server_guard);
+            return Ok(status().await);
+        }
+        *server_guard = None;
+    }
+    
+    let addr = match lan::server_addr().await {
+        Some(addr) => addr,
+        None => {
+            lan::enable(None).await?;
+            lan::server_addr().await.ok_or_else(|| "局域网播放未开启".to_string())?
+        }
+    };
+    
+    // 绑定到所有地址时使用检测到的局域网地址对外通告
+    let ip = if addr.ip().is_unspecified() {
+        lan::detect_lan_ip()?
+    } else {
+        addr.ip()
+    };
+    let IpAddr::V4(ip) = ip else {
+        return Err("DLNA 仅支持 IPv4 地址".to_string());
+    };
+    
+    let uuid = device_uuid()?;
+    let description_url = format!(
+        "http://{}:{}/dlna/{}/description.xml",
+        ip,
+        addr.port(),
+        DLNA_TOKEN.get()?
+    );
+    let socket = ssdp_socket(ip).map_err(|e| format!("无法监听 SSDP 端口: {}", e))?;
+    let multicast = SocketAddr::new(
+        SSDP_MULTICAST_ADDR.parse().unwrap_or(IpAddr::V4(Ipv4Addr::new(239, 255, 255, 250))),
+        SSDP_PORT,
+    );
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    
+    let location = description_url.clone();
+    let task = tokio::spawn(async move {
+        run_ssdp(socket, uuid, location, multicast, shutdown_rx).await;
+    });
+    
+    *server_guard = Some(DlnaHandle {
+        description_url,
+        shutdown_tx,
+        task,
+    });
+    drop(server_guard);
+    Ok(status().await)
+}
+
+/// 关闭 DLNA 媒体服务（发送下线通告）
+pub async fn disable() -> Result<(), String> {
+    let server = DLNA_SERVER.lock().await.take();
+    if let Some(server) = server {
+        let _ = server.shutdown_tx.send(());
+        let mut task = server.task;
+        if tokio::time::timeout(Duration::from_secs(1), &mut task).await.is_err() {
+            task.abort();
+        }
+    }
+    Ok(())
+}
+
+/// 获取 DLNA 媒体服务状态
+pub async fn status() -> DlnaStatus {
+    let server_guard = DLNA_SERVER.lock().await;
+    let description_url = server_guard
+        .as_ref()
+        .filter(|server| !server.task.is_finished())
+        .map(|server| server.description_url.clone());
+    DlnaStatus {
+        enabled: description_url.is_some(),
+        friendly_name: DLNA_FRIENDLY_NAME.to_string(),
+        description_url,
+    }
+}
+
+/// 创建 SSDP 组播套接字（允许与系统中其他 SSDP 服务共用端口）
+fn ssdp_socket(ip: Ipv4Addr) -> std::io::Result<UdpSocket> {
+    let multicast: Ipv4Addr = SSDP_MULTICAST_ADDR.parse().map_err(std::io::Error::other)?;
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    socket.bind(&SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, SSDP_PORT).into())?;
+    socket.join_multicast_v4(&multicast, &ip)?;
+    socket.set_multicast_if_v4(&ip)?;
+    socket.set_nonblocking(true)?;
+    UdpSocket::from_std(socket.into())
+}
+
+/// 设备通告的所有类型（NT）及对应的 USN
+fn notification_types(uuid: &str) -> Vec<(String, String)> {
+    let udn = format!("uuid:{}", uuid);
+    vec![
+        ("upnp:rootdevice".to_string(), format!("{}::upnp:rootdevice", udn)),
+        (udn.clone(), udn.clone()),
+        (MEDIA_SERVER_TYPE.to_string(), format!("{}::{}", udn, MEDIA_SERVER_TYPE)),
+        (CONTENT_DIRECTORY_TYPE.to_string(), format!("{}::{}", udn, CONTENT_DIRECTORY_TYPE)),
+        (CONNECTION_MANAGER_TYPE.to_string(), format!("{}::{}", udn, CONNECTION_MANAGER_TYPE)),
+    ]
+}
+
+/// SSDP 任务：定期向 multicast 发送上线通告，响应 M-SEARCH 搜索，退出时发送下线通告
+async fn run_ssdp(
+    socket: UdpSocket,
+    uuid: String,
+    location: String,
+    multicast: SocketAddr,
+    mut shutdown_rx: oneshot::Receiver<()>,
+) {
+    let types = notification_types(&uuid);
+    let mut interval = tokio::time::interval(Duration::from_secs(DLNA_NOTIFY_INTERVAL_SECS));
+    let mut buf = [0u8; 2048];
+    
+    loop {
+        tokio::select! {
+            _ = &mut shutdown_rx => break,
+            _ = interval.tick() => {
+                for (nt, usn) in &types {
+                    let message = format!(
+                        "NOTIFY * HTTP/1.1\r\nHOST: {}\r\nCACHE-CONTROL: max-age={}\r\n\
+                         LOCATION: {}\r\nNT: {}\r\nNTS: ssdp:alive\r\nSERVER: {}\r\nUSN: {}\r\n\r\n",
+                        multicast, DLNA_MAX_AGE_SECS, location, nt, server_header(), usn
+                    );
+                    let _ = socket.send_to(message.as_bytes(), multicast).await;
+                }
+            }
+            received = socket.recv_from(&mut buf) => {
+                let Ok((len, from)) = received else {
+                    continue;
+                };
+                let request = String::from_utf8_lossy(&buf[..len]);
+                let Some(st) = search_target(&request) else {
+                    continue;
+                };
+                for (nt, usn) in &types {
+                    if st != "ssdp:all" && st != *nt {
+                        continue;
+                    }
+                    let message = format!(
+                        "HTTP/1.1 200 OK\r\nCACHE-CONTROL: max-age={}\r\nEXT:\r\n\
+                         LOCATION: {}\r\nSERVER: {}\r\nST: {}\r\nUSN: {}\r\n\r\n",
+                        DLNA_MAX_AGE_SECS, location, server_header(), nt, usn
+                    );
+                    let _ = socket.send_to(message.as_bytes(), from).await;
+                }
+            }
+        }
+    }
+    
+    for (nt, usn) in &types {
+        let message = format!(
+            "NOTIFY * HTTP/1.1\r\nHOST: {}\r\nNT: {}\r\nNTS: ssdp:byebye\r\nUSN: {}\r\n\r\n",
+            multicast, nt, usn
+        );
+        let _ = socket.send_to(message.as_bytes(), multicast).await;
+    }
+}
+
+fn server_header() -> String {
+    format!("{}/1.0 UPnP/1.0 {}/{}", std::env::consts::OS, DLNA_FRIENDLY_NAME, env!("CARGO_PKG_VERSION"))
+}
+
+/// 解析 M-SEARCH 请求中的搜索目标（不是 M-SEARCH 时返回 None）
+fn search_target(request: &str) -> Option<String> {
+    let mut lines = request.lines();
+    if !lines.next()?.starts_with("M-SEARCH") {
+        return None;
+    }
+    ssdp_header(request, "ST")
+}
+
+/// 读取 SSDP 消息中的头部（不区分大小写）
+pub(crate) fn ssdp_header(message: &str, name: &str) -> Option<String> {
+    message.lines().skip(1).find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        key.trim()
+            .eq_ignore_ascii_case(name)
+            .then(|| value.trim().to_string())
+    })
+}
+
+/// DLNA 路由（挂载在局域网播放服务上）
+pub fn routes() -> Router {
+    Router::new()
+        .route("/dlna/:token/description.xml", get(handle_description))
+        .route("/dlna/:token/ContentDirectory.xml", get(handle_content_directory_scpd))
+        .route("/dlna/:token/ConnectionManager.xml", get(handle_connection_manager_scpd))
+        .route("/dlna/:token/control/ContentDirectory", post(handle_content_directory))
+        .route("/dlna/:token/control/ConnectionManager", post(handle_connection_manager))
+        .route("/dlna/:token/event/:service", any(handle_event_subscription))
+        .route("/dlna/:token/track/:bvid/:cid", get(handle_track).head(handle_track_head))
+        .route("/dlna/:token/download/:name", get(handle_download).head(handle_download))
+}
+
+fn check_token(token: &str) -> Result<(), (StatusCode, String)> {
+    if DLNA_TOKEN.is_valid(token) {
+        Ok(())
+    } else {
+        Err((StatusCode::FORBIDDEN, "访问令牌无效".to_string()))
+    }
+}
+
+fn xml_response(xml: String) -> Response {
+    (
+        [(header::CONTENT_TYPE, "text/xml; charset=\"utf-8\"")],
+        xml,
+    )
+        .into_response()
+}
+
+/// 设备描述
+async fn handle_description(Path(token): Path<String>) -> Result<Response, (StatusCode, String)> {
+    check_token(&token)?;
+    let uuid = device_uuid().map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    let service = |service_type: &str, name: &str| {
+        format!(
+            "<service><serviceType>{}</serviceType><serviceId>urn:upnp-org:serviceId:{}</serviceId>\
+             <SCPDURL>{}.xml</SCPDURL><controlURL>control/{}</controlURL>\
+             <eventSubURL>event/{}</eventSubURL></service>",
+            service_type, name, name, name, name
+        )
+    };
+    Ok(xml_response(format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\
+         <root xmlns=\"urn:schemas-upnp-org:device-1-0\" xmlns:dlna=\"urn:schemas-dlna-org:device-1-0\">\
+         <specVersion><major>1</major><minor>0</minor></specVersion><device>\
+         <deviceType>{}</deviceType><friendlyName>{}</friendlyName>\
+         <manufacturer>{}</manufacturer><modelName>{}</modelName><modelNumber>{}</modelNumber>\
+         <UDN>uuid:{}</UDN><dlna:X_DLNADOC>DMS-1.50</dlna:X_DLNADOC>\
+         <serviceList>{}{}</serviceList></device></root>",
+        MEDIA_SERVER_TYPE,
+        DLNA_FRIENDLY_NAME,
+        DLNA_FRIENDLY_NAME,
+        DLNA_FRIENDLY_NAME,
+        env!("CARGO_PKG_VERSION"),
+        uuid,
+        service(CONTENT_DIRECTORY_TYPE, "ContentDirectory"),
+        service(CONNECTION_MANAGER_TYPE, "ConnectionManager"),
+    )))
+}
+
+/// SCPD 动作：名称和参数列表（参数名、方向、关联的状态变量）
+type ScpdAction<'a> = (&'a str, &'a [(&'a str, &'a str, &'a str)]);
+
+/// 生成服务描述（SCPD）
+fn scpd(actions: &[ScpdAction], variables: &[(&str, &str)]) -> String {
+    let actions: String = actions
+        .iter()
+        .map(|(name, arguments)| {
+            let arguments: String = arguments
+                .iter()
+                .map(|(argument, direction, variable)| format!(
+                    "<argument><name>{}</name><direction>{}</direction>\
+                     <relatedStateVariable>{}</relatedStateVariable></argument>",
+                    argument, direction, variable
+                ))
+                .collect();
+            format!("<action><name>{}</name><argumentList>{}</argumentList></action>", name, arguments)
+        })
+        .collect();
+    let variables: String = variables
+        .iter()
+        .map(|(name, data_type)| format!(
+            "<stateVariable sendEvents=\"no\"><name>{}</name><dataType>{}</dataType></stateVariable>",
+            name, data_type
+        ))
+        .collect();
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\
+         <scpd xmlns=\"urn:schemas-upnp-org:service-1-0\">\
+         <specVersion><major>1</major><minor>0</minor></specVersion>\
+         <actionList>{}</actionList><serviceStateTable>{}</serviceStateTable></scpd>",
+        actions, variables
+    )
+}
+
+async fn handle_content_directory_scpd(Path(token): Path<String>) -> Result<Response, (StatusCode, String)> {
+    check_token(&token)?;
+    Ok(xml_response(scpd(
+        &[
+            ("Browse", &[
+                ("ObjectID", "in", "A_ARG_TYPE_ObjectID"),
+                ("BrowseFlag", "in", "A_ARG_TYPE_BrowseFlag"),
+                ("Filter", "in", "A_ARG_TYPE_Filter"),
+                ("StartingIndex", "in", "A_ARG_TYPE_Index"),
+                ("RequestedCount", "in", "A_ARG_TYPE_Count"),
+                ("SortCriteria", "in", "A_ARG_TYPE_SortCriteria"),
+                ("Result", "out", "A_ARG_TYPE_Result"),
+                ("NumberReturned", "out", "A_ARG_TYPE_Count"),
+                ("TotalMatches", "out", "A_ARG_TYPE_Count"),
+                ("UpdateID", "out", "A_ARG_TYPE_UpdateID"),
+            ]),
+            ("GetSearchCapabilities", &[("SearchCaps", "out", "SearchCapabilities")]),
+            ("GetSortCapabilities", &[("SortCaps", "out", "SortCapabilities")]),
+            ("GetSystemUpdateID", &[("Id", "out", "SystemUpdateID")]),
+        ],
+        &[
+            ("A_ARG_TYPE_ObjectID", "string"),
+            ("A_ARG_TYPE_BrowseFlag", "string"),
+            ("A_ARG_TYPE_Filter", "string"),
+            ("A_ARG_TYPE_Index", "ui4"),
+            ("A_ARG_TYPE_Count", "ui4"),
+            ("A_ARG_TYPE_SortCriteria", "string"),
+            ("A_ARG_TYPE_Result", "string"),
+            ("A_ARG_TYPE_UpdateID", "ui4"),
+            ("SearchCapabilities", "string"),
+            ("SortCapabilities", "string"),
+            ("SystemUpdateID", "ui4"),
+        ],
+    )))
+}
+
+async fn handle_connection_manager_scpd(Path(token): Path<String>) -> Result<Response, (StatusCode, String)> {
+    check_token(&token)?;
+    Ok(xml_response(scpd(
+        &[
+            ("GetProtocolInfo", &[
+                ("Source", "out", "SourceProtocolInfo"),
+                ("Sink", "out", "SinkProtocolInfo"),
+            ]),
+            ("GetCurrentConnectionIDs", &[("ConnectionIDs", "out", "CurrentConnectionIDs")]),
+        ],
+        &[
+            ("SourceProtocolInfo", "string"),
+            ("SinkProtocolInfo", "string"),
+            ("CurrentConnectionIDs", "string"),
+        ],
+    )))
+}
+
+/// 事件订阅：不推送事件，只返回订阅成功，避免部分设备因订阅失败而拒绝连接
+async fn handle_event_subscription(
+    Path((token, _service)): Path<(String, String)>,
+) -> Result<Response, (StatusCode, String)> {
+    check_token(&token)?;
+    let uuid = device_uuid().map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    Ok((
+        [
+            (header::HeaderName::from_static("sid"), format!("uuid:{}", uuid)),
+            (header::HeaderName::from_static("timeout"), format!("Second-{}", DLNA_MAX_AGE_SECS)),
+        ],
+        "",
+    )
+        .into_response())
+}
+
+/// 只允许播放媒体库收藏中的分 P（DLNA 令牌随通告公开，不能用来播放任意视频）
+fn check_in_library(bvid: &str, cid: u64) -> Result<(), (StatusCode, String)> {
+    lan::check_bvid(bvid)?;
+    if library::favorites().iter().any(|track| track.bvid == bvid && track.cid == cid) {
+        Ok(())
+    } else {
+        Err((StatusCode::NOT_FOUND, "媒体库中没有该曲目".to_string()))
+    }
+}
+
+/// 播放媒体库中的分 P（与局域网播放的曲目地址相同，只是使用 DLNA 令牌）
+async fn handle_track(
+    Path((token, bvid, cid)): Path<(String, String, u64)>,
+    headers: HeaderMap,
+) -> Result<Response<Body>, (StatusCode, String)> {
+    check_token(&token)?;
+    check_in_library(&bvid, cid)?;
+    let range = headers
+        .get(header::RANGE)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string());
+    proxy::serve_track(&bvid, cid, None, range).await
+}
+
+async fn handle_track_head(
+    Path((token, bvid, cid)): Path<(String, String, u64)>,
+    headers: HeaderMap,
+) -> Result<Response<Body>, (StatusCode, String)> {
+    check_token(&token)?;
+    check_in_library(&bvid, cid)?;
+    let range = headers
+        .get(header::RANGE)
+        .and_then(|v| v.to_str().ok());
+    proxy::serve_track_head(&bvid, cid, None, range).await
+}
+
+/// 提供下载目录中的文件
+async fn handle_download(
+    Path((token, name)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Result<Response<Body>, (StatusCode, String)> {
+    check_token(&token)?;
+    lan::serve_download(&name, &headers).await
+}
+
+/// 从 SOAPACTION 头中取出动作名（`"urn:...:1#Browse"` → `Browse`）
+fn soap_action(headers: &HeaderMap) -> String {
+    headers
+        .get("soapaction")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim_matches('"').rsplit('#').next())
+        .unwrap_or("")
+        .to_string()
+}
+
+/// 生成 SOAP 响应
+pub(crate) fn soap_envelope(body: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\
+         <s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" \
+         s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\">\
+         <s:Body>{}</s:Body></s:Envelope>",
+        body
+    )
+}
+
+fn soap_response(service_type: &str, action: &str, values: &[(&str, String)]) -> Response {
+    let values: String = values
+        .iter()
+        .map(|(name, value)| format!("<{}>{}</{}>", name, escape_html(value), name))
+        .collect();
+    xml_response(soap_envelope(&format!(
+        "<u:{}Response xmlns:u=\"{}\">{}</u:{}Response>",
+        action, service_type, values, action
+    )))
+}
+
+/// SOAP 错误（UPnP 401 Invalid Action 等）
+fn soap_fault(code: u16, description: &str) -> Response {
+    let body = soap_envelope(&format!(
+        "<s:Fault><faultcode>s:Client</faultcode><faultstring>UPnPError</faultstring>\
+         <detail><UPnPError xmlns=\"urn:schemas-upnp-org:control-1-0\">\
+         <errorCode>{}</errorCode><errorDescription>{}</errorDescription>\
+         </UPnPError></detail></s:Fault>",
+        code, description
+    ));
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        [(header::CONTENT_TYPE, "text/xml; charset=\"utf-8\"")],
+        body,
+    )
+        .into_response()
+}
+
+async fn handle_connection_manager(
+    Path(token): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, String)> {
+    check_token(&token)?;
+    let action = soap_action(&headers);
+    Ok(match action.as_str() {
+        "GetProtocolInfo" => soap_response(CONNECTION_MANAGER_TYPE, &action, &[
+            ("Source", "http-get:*:audio/mp4:*,http-get:*:video/mp4:*,http-get:*:audio/mpeg:*,http-get:*:audio/flac:*".to_string()),
+            ("Sink", String::new()),
+        ]),
+        "GetCurrentConnectionIDs" => soap_response(CONNECTION_MANAGER_TYPE, &action, &[
+            ("ConnectionIDs", "0".to_string()),
+        ]),
+        _ => soap_fault(401, "Invalid Action"),
+    })
+}
+
+async fn handle_content_directory(
+    Path(token): Path<String>,
+    headers: HeaderMap,
+    body: String,
+) -> Result<Response, (StatusCode, String)> {
+    check_token(&token)?;
+    let base_url = media_base_url(&headers, &token).await;
+    let action = soap_action(&headers);
+    let update_id = library::revision().to_string();
+    Ok(match action.as_str() {
+        "GetSearchCapabilities" => soap_response(CONTENT_DIRECTORY_TYPE, &action, &[("SearchCaps", String::new())]),
+        "GetSortCapabilities" => soap_response(CONTENT_DIRECTORY_TYPE, &action, &[("SortCaps", String::new())]),
+        "GetSystemUpdateID" => soap_response(CONTENT_DIRECTORY_TYPE, &action, &[("Id", update_id)]),
+        "Browse" => {
+            let object_id = xml_text(&body, "ObjectID").unwrap_or_else(|| "0".to_string());
+            let flag = xml_text(&body, "BrowseFlag").unwrap_or_default();
+            let start = xml_text(&body, "StartingIndex").and_then(|v| v.parse().ok()).unwrap_or(0);
+            let count = xml_text(&body, "RequestedCount").and_then(|v| v.parse().ok()).unwrap_or(0);
+            match browse(base_url.as_deref(), &object_id, &flag, start, count) {
+                Some((didl, returned, total)) => soap_response(CONTENT_DIRECTORY_TYPE, &action, &[
+                    ("Result", didl),
+                    ("NumberReturned", returned.to_string()),
+                    ("TotalMatches", total.to_string()),
+                    ("UpdateID", update_id),
+                ]),
+                None => soap_fault(701, "No such object"),
+            }
+        }
+        _ => soap_fault(401, "Invalid Action"),
+    })
+}
+
+/// 媒体库中的一项
+enum DidlObject {
+    Container { id: &'static str, title: &'static str, child_count: usize },
+    Item { id: String, parent_id: &'static str, title: String, creator: Option<String>, url: String, mime: &'static str },
+}
+
+impl DidlObject {
+    fn to_xml(&self) -> String {
+        match self {
+            DidlObject::Container { id, title, child_count } => format!(
+                "<container id=\"{}\" parentID=\"0\" restricted=\"1\" childCount=\"{}\">\
+                 <dc:title>{}</dc:title><upnp:class>object.container.storageFolder</upnp:class></container>",
+                id, child_count, title
+            ),
+            DidlObject::Item { id, parent_id, title, creator, url, mime } => format!(
+                "<item id=\"{}\" parentID=\"{}\" restricted=\"1\"><dc:title>{}</dc:title>{}\
+                 <upnp:class>object.item.audioItem.musicTrack</upnp:class>\
+                 <res protocolInfo=\"http-get:*:{}:*\">{}</res></item>",
+                escape_html(id),
+                parent_id,
+                escape_html(title),
+                creator
+                    .as_deref()
+                    .map(|c| format!("<dc:creator>{}</dc:creator>", escape_html(c)))
+                    .unwrap_or_default(),
+                mime,
+                escape_html(url)
+            ),
+        }
+    }
+}
+
+/// 生成 DIDL-Lite 文档
+pub(crate) fn didl_lite(objects: &str) -> String {
+    format!(
+        "<DIDL-Lite xmlns=\"urn:schemas-upnp-org:metadata-1-0/DIDL-Lite/\" \
+         xmlns:dc=\"http://purl.org/dc/elements/1.1/\" \
+         xmlns:upnp=\"urn:schemas-upnp-org:metadata-1-0/upnp/\">{}</DIDL-Lite>",
+        objects
+    )
+}
+
+/// 媒体库播放地址的前缀（`http://地址/dlna/令牌`，地址取设备访问时使用的 Host）
+async fn media_base_url(headers: &HeaderMap, token: &str) -> Option<String> {
+    let host = match headers.get(header::HOST).and_then(|v| v.to_str().ok()) {
+        Some(host) => host.to_string(),
+        None => lan::server_addr().await?.to_string(),
+    };
+    Some(format!("http://{}/dlna/{}", host, token))
+}
+
+/// 列出容器下的所有对象（base_url 为 None 时无法生成播放地址，只能列出根目录）
+fn children(base_url: Option<&str>, container: &str) -> Option<Vec<DidlObject>> {
+    match container {
+        "0" => {
+            Some(vec![
+                DidlObject::Container { id: "favorites", title: "收藏", child_count: library::favorites().len() },
+                DidlObject::Container { id: "downloads", title: "下载", child_count: library::download_files().len() },
+            ])
+        }
+        "favorites" => {
+            let base_url = base_url?;
+            Some(library::favorites()
+                .into_iter()
+                .map(|track| DidlObject::Item {
+                    id: format!("favorites/{}/{}", track.bvid, track.cid),
+                    parent_id: "favorites",
+                    url: format!("{}/track/{}/{}", base_url, track.bvid, track.cid),
+                    title: track.title,
+                    creator: track.author,
+                    mime: "audio/mp4",
+                })
+                .collect())
+        }
+        "downloads" => {
+            let base_url = base_url?;
+            Some(library::download_files()
+                .into_iter()
+                .map(|file| file.name)
+                .map(|name| DidlObject::Item {
+                    id: format!("downloads/{}", name),
+                    parent_id: "downloads",
+                    url: format!("{}/download/{}", base_url, urlencoding::encode(&name)),
+                    title: name.rsplit_once('.').map(|(stem, _)| stem).unwrap_or(&name).to_string(),
+                    creator: None,
+                    mime: library::media_mime(&name),
+                })
+                .collect())
+        }
+        _ => None,
+    }
+}
+
+/// Browse 动作（返回 DIDL-Lite、本次返回数量和总数）
+fn browse(
+    base_url: Option<&str>,
+    object_id: &str,
+    flag: &str,
+    start: usize,
+    count: usize,
+) -> Option<(String, usize, usize)> {
+    if flag == "BrowseMetadata" {
+        let object = match object_id {
+            "0" => "<container id=\"0\" parentID=\"-1\" restricted=\"1\">\
+                    <dc:title>纲一下</dc:title><upnp:class>object.container</upnp:class></container>"
+                .to_string(),
+            _ => {
+                let parent = object_id.split('/').next().unwrap_or("");
+                let siblings = if parent == object_id { children(base_url, "0")? } else { children(base_url, parent)? };
+                siblings
+                    .iter()
+                    .find(|o| match o {
+                        DidlObject::Container { id, .. } => *id == object_id,
+                        DidlObject::Item { id, .. } => id == object_id,
+                    })?
+                    .to_xml()
+            }
+        };
+        return Some((didl_lite(&object), 1, 1));
+    }
+    
+    let objects = children(base_url, object_id)?;
+    let total = objects.len();
+    let count = if count == 0 { total } else { count };
+    let xml: String = objects.iter().skip(start).take(count).map(DidlObject::to_xml).collect();
+    let returned = total.saturating_sub(start).min(count);
+    Some((didl_lite(&xml), returned, total))
+}
+
+/// 解码 XML 实体
+fn xml_unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+/// 查找所有指定名称的元素（忽略命名空间前缀），返回元素内部的原始内容
+pub(crate) fn xml_elements<'a>(xml: &'a str, name: &str) -> Vec<&'a str> {
+    let local = |tag: &str| -> String {
+        let tag = tag.split(|c: char| c.is_whitespace() || c == '/' || c == '>').next().unwrap_or("");
+        tag.rsplit(':').next().unwrap_or("").to_string()
+    };
+    
+    let mut result = Vec::new();
+    let mut pos = 0;
+    while let Some(offset) = xml[pos..].find('<') {
+        let open = pos + offset;
+        let Some(close) = xml[open..].find('>').map(|i| open + i) else {
+            break;
+        };
+        let tag = &xml[open + 1..close];
+        pos = close + 1;
+        if tag.starts_with(['/', '?', '!']) || tag.ends_with('/') || local(tag) != name {
+            continue;
+        }
+        
+        // 查找配对的结束标签（处理同名元素嵌套）
+        let content_start = close + 1;
+        let mut depth = 1;
+        let mut scan = content_start;
+        while let Some(offset) = xml[scan..].find('<') {
+            let tag_start = scan + offset;
+            let Some(tag_end) = xml[tag_start..].find('>').map(|i| tag_start + i) else {
+                break;
+            };
+            let inner = &xml[tag_start + 1..tag_end];
+            scan = tag_end + 1;
+            if let Some(closing) = inner.strip_prefix('/') {
+                if local(closing) == name {
+                    depth -= 1;
+                    if depth == 0 {
+                        result.push(&xml[content_start..tag_start]);
+                        break;
+                    }
+                }
+            } else if !inner.ends_with('/') && local(inner) == name {
+                depth += 1;
+            }
+        }
+    }
+    result
+}
+
+/// 获取第一个指定名称元素的文本（已解码实体）
+pub(crate) fn xml_text(xml: &str, name: &str) -> Option<String> {
+    xml_elements(xml, name)
+        .first()
+        .map(|content| xml_unescape(content.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lan::LanTrack;
+    
+    const MEDIA_SERVER_SEARCH: &str = "urn:schemas-upnp-org:device:MediaServer:1";
+    
+    /// 本地模拟的播放设备：接收 SSDP 消息、发送 M-SEARCH、读取设备描述并调用 ContentDirectory
+    struct StandInRenderer {
+        socket: UdpSocket,
+        client: reqwest::Client,
+    }
+    
+    impl StandInRenderer {
+        async fn new() -> Self {
+            Self {
+                socket: UdpSocket::bind("127.0.0.1:0").await.unwrap(),
+                client: reqwest::Client::new(),
+            }
+        }
+        
+        fn addr(&self) -> SocketAddr {
+            self.socket.local_addr().unwrap()
+        }
+        
+        /// 接收 SSDP 消息，直到出现符合条件的消息
+        async fn recv_until(&self, matches: impl Fn(&str) -> bool) -> String {
+            let mut buf = [0u8; 2048];
+            loop {
+                let (len, _) = tokio::time::timeout(Duration::from_secs(5), self.socket.recv_from(&mut buf))
+                    .await
+                    .expect("等待 SSDP 消息超时")
+                    .unwrap();
+                let message = String::from_utf8_lossy(&buf[..len]).to_string();
+                if matches(&message) {
+                    return message;
+                }
+            }
+        }
+        
+        /// 接收指定类型（NTS）的通告
+        async fn recv_notify(&self, nts: &str) -> String {
+            self.recv_until(|message| {
+                message.starts_with("NOTIFY * HTTP/1.1") && ssdp_header(message, "NTS").as_deref() == Some(nts)
+            })
+            .await
+        }
+        
+        /// 搜索媒体服务，返回描述文件地址
+        async fn search(&self, server: SocketAddr) -> String {
+            let request = format!(
+                "M-SEARCH * HTTP/1.1\r\nHOST: 239.255.255.250:1900\r\nMAN: \"ssdp:discover\"\r\nMX: 1\r\nST: {}\r\n\r\n",
+                MEDIA_SERVER_SEARCH
+            );
+            self.socket.send_to(request.as_bytes(), server).await.unwrap();
+            let response = self.recv_until(|message| message.starts_with("HTTP/1.1 200 OK")).await;
+            assert_eq!(ssdp_header(&response, "ST").as_deref(), Some(MEDIA_SERVER_SEARCH));
+            ssdp_header(&response, "LOCATION").expect("响应缺少 LOCATION")
+        }
+        
+        /// 调用 SOAP 动作，返回状态码和响应体
+        async fn soap(&self, control_url: &str, service_type: &str, action: &str, arguments: &str) -> (u16, String) {
+            let body = soap_envelope(&format!(
+                "<u:{} xmlns:u=\"{}\">{}</u:{}>",
+                action, service_type, arguments, action
+            ));
+            let response = self.client
+                .post(control_url)
+                .header("SOAPACTION", format!("\"{}#{}\"", service_type, action))
+                .header("Content-Type", "text/xml; charset=\"utf-8\"")
+                .body(body)
+                .send()
+                .await
+                .unwrap();
+            (response.status().as_u16(), response.text().await.unwrap())
+        }
+        
+        /// 浏览容器，返回解码后的 DIDL-Lite 和返回数量
+        async fn browse(&self, control_url: &str, object_id: &str, flag: &str) -> (String, usize) {
+            let (status, body) = self.soap(
+                control_url,
+                CONTENT_DIRECTORY_TYPE,
+                "Browse",
+                &format!(
+                    "<ObjectID>{}</ObjectID><BrowseFlag>{}</BrowseFlag><Filter>*</Filter>\
+                     <StartingIndex>0</StartingIndex><RequestedCount>0</RequestedCount><SortCriteria></SortCriteria>",
+                    object_id, flag
+                ),
+            ).await;
+            assert_eq!(status, 200, "{}", body);
+            let result = xml_elements(&body, "Result").first().map(|r| xml_unescape(r)).unwrap();
+            let returned = xml_text(&body, "NumberReturned").and_then(|v| v.parse().ok()).unwrap();
+            (result, returned)
+        }
+    }
+    
+    #[tokio::test]
+    async fn stand_in_renderer_browses_library_without_lan_token() {
+        let dir = std::env::temp_dir().join(format!("dlna-test-{}", rand::random::<u64>()));
+        let downloads = dir.join("downloads");
+        fs::create_dir_all(&downloads).unwrap();
+        fs::write(downloads.join("相声 & 小品.m4a"), b"0123456789").unwrap();
+        paths::init_in(&dir);
+        library::set_library(
+            vec![LanTrack {
+                bvid: "BV1xx411c7mD".to_string(),
+                cid: 42,
+                title: "报菜名 <完整版> & 返场".to_string(),
+                author: Some("德云社".to_string()),
+                ..Default::default()
+            }],
+            Some(downloads.to_string_lossy().to_string()),
+        );
+        let lan_token = lan::lan_token().unwrap();
+        let dlna_token = DLNA_TOKEN.get().unwrap();
+        assert_ne!(lan_token, dlna_token);
+        
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let http_addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, routes()).await.unwrap();
+        });
+        
+        // 通告发送给模拟设备，代替局域网组播
+        let renderer = StandInRenderer::new().await;
+        let ssdp = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let ssdp_addr = ssdp.local_addr().unwrap();
+        let location = format!("http://{}/dlna/{}/description.xml", http_addr, dlna_token);
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+        let ssdp_task = tokio::spawn(run_ssdp(
+            ssdp,
+            device_uuid().unwrap(),
+            location.clone(),
+            renderer.addr(),
+            shutdown_rx,
+        ));
+        
+        // SSDP：通告和搜索响应中都只有 DLNA 令牌
+        let notify = renderer.recv_notify("ssdp:alive").await;
+        assert_eq!(ssdp_header(&notify, "LOCATION").as_deref(), Some(location.as_str()));
+        assert!(!notify.contains(&lan_token));
+        assert_eq!(renderer.search(ssdp_addr).await, location);
+        
+        // 设备描述
+        let description = renderer.client.get(&location).send().await.unwrap().text().await.unwrap();
+        assert!(description.contains(&format!("<deviceType>{}</deviceType>", MEDIA_SERVER_TYPE)));
+        let control_url = xml_elements(&description, "service")
+            .into_iter()
+            .find(|service| xml_text(service, "serviceType").as_deref() == Some(CONTENT_DIRECTORY_TYPE))
+            .and_then(|service| xml_text(service, "controlURL"))
+            .unwrap();
+        let control_url = format!("{}/{}", location.rsplit_once('/').unwrap().0, control_url);
+        
+        // 根目录
+        let (root, returned) = renderer.browse(&control_url, "0", "BrowseDirectChildren").await;
+        assert_eq!(returned, 2);
+        assert!(root.starts_with("<DIDL-Lite xmlns=\"urn:schemas-upnp-org:metadata-1-0/DIDL-Lite/\""));
+        assert!(root.contains("<container id=\"favorites\" parentID=\"0\" restricted=\"1\" childCount=\"1\">"));
+        assert!(root.contains("<container id=\"downloads\" parentID=\"0\" restricted=\"1\" childCount=\"1\">"));
+        
+        // 收藏：标题转义，播放地址使用 DLNA 令牌
+        let (favorites, returned) = renderer.browse(&control_url, "favorites", "BrowseDirectChildren").await;
+        assert_eq!(returned, 1);
+        let track_url = format!("http://{}/dlna/{}/track/BV1xx411c7mD/42", http_addr, dlna_token);
+        assert_eq!(
+            favorites,
+            didl_lite(&format!(
+                "<item id=\"favorites/BV1xx411c7mD/42\" parentID=\"favorites\" restricted=\"1\">\
+                 <dc:title>报菜名 &lt;完整版&gt; &amp; 返场</dc:title><dc:creator>德云社</dc:creator>\
+                 <upnp:class>object.item.audioItem.musicTrack</upnp:class>\
+                 <res protocolInfo=\"http-get:*:audio/mp4:*\">{}</res></item>",
+                track_url
+            ))
+        );
+        assert!(!favorites.contains(&lan_token));
+        
+        // 单个对象的元数据
+        let (metadata, returned) = renderer.browse(&control_url, "favorites/BV1xx411c7mD/42", "BrowseMetadata").await;
+        assert_eq!(returned, 1);
+        assert_eq!(metadata, favorites);
+        
+        // 下载：可通过 DIDL 中的地址直接读取文件
+        let (downloads, _) = renderer.browse(&control_url, "downloads", "BrowseDirectChildren").await;
+        let res = xml_elements(&downloads, "res").first().map(|r| xml_unescape(r)).unwrap();
+        assert!(res.starts_with(&format!("http://{}/dlna/{}/download/", http_addr, dlna_token)));
+        let file = renderer.client.get(&res).send().await.unwrap();
+        assert_eq!(file.headers()["content-type"], "audio/mp4");
+        assert_eq!(file.bytes().await.unwrap().as_ref(), b"0123456789");
+        
+        // 媒体库之外的视频、分 P 和文件都不能通过 DLNA 令牌访问
+        for path in ["track/BV1ab411c7XY/42", "track/BV1xx411c7mD/43", "download/..%2Flan_token"] {
+            let url = format!("http://{}/dlna/{}/{}", http_addr, dlna_token, path);
+            let response = renderer.client.get(&url).send().await.unwrap();
+            assert_eq!(response.status().as_u16(), 404, "{}", path);
+            let response = renderer.client.head(&url).send().await.unwrap();
+            assert_eq!(response.status().as_u16(), 404, "{}", path);
+        }
+        
+        // 未知动作和错误的令牌
+        let (status, fault) = renderer.soap(&control_url, CONTENT_DIRECTORY_TYPE, "Search", "").await;
+        assert_eq!(status, 500);
+        assert_eq!(xml_text(&fault, "errorCode").as_deref(), Some("401"));
+        let lan_control_url = control_url.replace(&dlna_token, &lan_token);
+        let (status, _) = renderer.soap(&lan_control_url, CONTENT_DIRECTORY_TYPE, "GetSystemUpdateID", "").await;
+        assert_eq!(status, 403);
+        
+        // 下线通告
+        let _ = shutdown_tx.send(());
+        let byebye = renderer.recv_notify("ssdp:byebye").await;
+        assert!(ssdp_header(&byebye, "USN").is_some_and(|usn| usn.contains(&device_uuid().unwrap())));
+        ssdp_task.await.unwrap();
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,380 @@
+//! DLNA 播放设备控制模块
+//! 
+//! 搜索局域网中的 UPnP MediaRenderer，把当前曲目的局域网播放地址推送过去，
+//! 并通过 AVTransport 服务控制播放、暂停、停止和跳转
+
+use crate::constants::{
+    DLNA_DISCOVERY_TIMEOUT_MS, DLNA_REQUEST_TIMEOUT_SECS, SSDP_MULTICAST_ADDR, SSDP_PORT,
+};
+use crate::dlna::{didl_lite, soap_envelope, ssdp_header, xml_elements, xml_text};
+use crate::lan::{self, escape_html, LanTrack};
+use lazy_static::lazy_static;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+use tokio::net::UdpSocket;
+
+const MEDIA_RENDERER_TYPE: &str = "urn:schemas-upnp-org:device:MediaRenderer:1";
+const AV_TRANSPORT_TYPE: &str = "urn:schemas-upnp-org:service:AVTransport:1";
+
+/// 局域网中的播放设备
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RendererInfo {
+    /// 设备 UDN
+    pub id: String,
+    pub name: String,
+    /// 设备描述地址
+    pub location: String,
+    /// AVTransport 控制地址
+    pub av_transport_url: String,
+}
+
+lazy_static! {
+    /// 已发现的播放设备（按 UDN 索引）
+    static ref RENDERERS: RwLock<HashMap<String, RendererInfo>> = RwLock::new(HashMap::new());
+}
+
+/// 局域网请求使用的 HTTP 客户端（不携带 B 站 Cookie 和请求头）
+fn lan_client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(DLNA_REQUEST_TIMEOUT_SECS))
+        .build()
+        .map_err(|e| format!("创建 HTTP 客户端失败: {}", e))
+}
+
+/// 搜索局域网中的播放设备
+pub async fn discover(timeout_ms: Option<u64>) -> Result<Vec<RendererInfo>, String> {
+    let ip = match lan::detect_lan_ip()? {
+        IpAddr::V4(ip) => ip,
+        IpAddr::V6(_) => return Err("DLNA 仅支持 IPv4 地址".to_string()),
+    };
+    let socket = UdpSocket::bind(SocketAddr::new(IpAddr::V4(ip), 0))
+        .await
+        .map_err(|e| format!("创建搜索套接字失败: {}", e))?;
+    let multicast = SocketAddr::new(
+        SSDP_MULTICAST_ADDR.parse().unwrap_or(IpAddr::V4(Ipv4Addr::new(239, 255, 255, 250))),
+        SSDP_PORT,
+    );
+    let request = format!(
+        "M-SEARCH * HTTP/1.1\r\nHOST: {}\r\nMAN: \"ssdp:discover\"\r\nMX: 2\r\nST: {}\r\n\r\n",
+        multicast, MEDIA_RENDERER_TYPE
+    );
+    // UDP 可能丢包，多发送一次
+    for _ in 0..2 {
+        socket
+            .send_to(request.as_bytes(), multicast)
+            .await
+            .map_err(|e| format!("发送搜索请求失败: {}", e))?;
+    }
+    
+    let deadline = Instant::now() + Duration::from_millis(timeout_ms.unwrap_or(DLNA_DISCOVERY_TIMEOUT_MS));
+    let mut locations: Vec<String> = Vec::new();
+    let mut buf = [0u8; 2048];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        match tokio::time::timeout(remaining, socket.recv_from(&mut buf)).await {
+            Ok(Ok((len, _))) => {
+                let response = String::from_utf8_lossy(&buf[..len]);
+                if let Some(location) = ssdp_header(&response, "LOCATION") {
+                    if !locations.contains(&location) {
+                        locations.push(location);
+                    }
+                }
+            }
+            Ok(Err(_)) => continue,
+            Err(_) => break,
+        }
+    }
+    
+    let results = futures::future::join_all(locations.into_iter().map(connect)).await;
+    Ok(results.into_iter().filter_map(Result::ok).collect())
+}
+
+/// 读取设备描述并记录播放设备（也可用于直接添加无法通过组播发现的设备）
+pub async fn connect(location: String) -> Result<RendererInfo, String> {
+    let base = reqwest::Url::parse(&location).map_err(|_| format!("无效的设备地址: {}", location))?;
+    let description = lan_client()?
+        .get(base.clone())
+        .send()
+        .await
+        .map_err(|e| format!("读取设备描述失败: {}", e))?
+        .text()
+        .await
+        .map_err(|e| format!("读取设备描述失败: {}", e))?;
+    
+    let control_url = xml_elements(&description, "service")
+        .into_iter()
+        .find(|service| {
+            xml_text(service, "serviceType")
+                .map(|t| t.starts_with("urn:schemas-upnp-org:service:AVTransport:"))
+                .unwrap_or(false)
+        })
+        .and_then(|service| xml_text(service, "controlURL"))
+        .ok_or_else(|| "设备不支持 AVTransport 服务".to_string())?;
+    
+    // 控制地址相对于 URLBase（没有时相对于描述地址）
+    let base = xml_text(&description, "URLBase")
+        .and_then(|url| reqwest::Url::parse(&url).ok())
+        .unwrap_or(base);
+    let av_transport_url = base
+        .join(&control_url)
+        .map_err(|_| format!("无效的控制地址: {}", control_url))?
+        .to_string();
+    
+    let renderer = RendererInfo {
+        id: xml_text(&description, "UDN").unwrap_or_else(|| location.clone()),
+        name: xml_text(&description, "friendlyName").unwrap_or_else(|| location.clone()),
+        location,
+        av_transport_url,
+    };
+    if let Ok(mut renderers) = RENDERERS.write() {
+        renderers.insert(renderer.id.clone(), renderer.clone());
+    }
+    Ok(renderer)
+}
+
+fn get_renderer(renderer_id: &str) -> Result<RendererInfo, String> {
+    RENDERERS
+        .read()
+        .ok()
+        .and_then(|renderers| renderers.get(renderer_id).cloned())
+        .ok_or_else(|| "未找到播放设备，请重新搜索".to_string())
+}
+
+/// 调用 AVTransport 动作
+async fn av_transport(renderer: &RendererInfo, action: &str, args: &[(&str, String)]) -> Result<String, String> {
+    let args: String = std::iter::once(("InstanceID", "0".to_string()))
+        .chain(args.iter().map(|(name, value)| (*name, value.clone())))
+        .map(|(name, value)| format!("<{}>{}</{}>", name, escape_html(&value), name))
+        .collect();
+    let body = soap_envelope(&format!(
+        "<u:{} xmlns:u=\"{}\">{}</u:{}>",
+        action, AV_TRANSPORT_TYPE, args, action
+    ));
+    
+    let response = lan_client()?
+        .post(&renderer.av_transport_url)
+        .header("Content-Type", "text/xml; charset=\"utf-8\"")
+        .header("SOAPACTION", format!("\"{}#{}\"", AV_TRANSPORT_TYPE, action))
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| format!("连接播放设备失败: {}", e))?;
+    let status = response.status();
+    let text = response.text().await.unwrap_or_default();
+    if !status.is_success() {
+        let reason = xml_text(&text, "errorDescription").unwrap_or_else(|| status.to_string());
+        return Err(format!("播放设备拒绝了 {} 请求: {}", action, reason));
+    }
+    Ok(text)
+}
+
+/// 在播放设备上播放曲目（使用局域网播放地址，需要先开启局域网播放）
+pub async fn play(renderer_id: &str, track: LanTrack) -> Result<(), String> {
+    let renderer = get_renderer(renderer_id)?;
+    let url = lan::track_url(&track.bvid, track.cid).await?;
+    let metadata = didl_lite(&format!(
+        "<item id=\"{}/{}\" parentID=\"-1\" restricted=\"1\"><dc:title>{}</dc:title>{}\
+         <upnp:class>object.item.audioItem.musicTrack</upnp:class>\
+         <res protocolInfo=\"http-get:*:audio/mp4:*\">{}</res></item>",
+        escape_html(&track.bvid),
+        track.cid,
+        escape_html(&track.title),
+        track.author
+            .as_deref()
+            .map(|a| format!("<dc:creator>{}</dc:creator>", escape_html(a)))
+            .unwrap_or_default(),
+        escape_html(&url)
+    ));
+    
+    av_transport(&renderer, "SetAVTransportURI", &[
+        ("CurrentURI", url),
+        ("CurrentURIMetaData", metadata),
+    ])
+    .await?;
+    av_transport(&renderer, "Play", &[("Speed", "1".to_string())]).await?;
+    lan::set_now_playing(track);
+    Ok(())
+}
+
+/// 暂停
+pub async fn pause(renderer_id: &str) -> Result<(), String> {
+    av_transport(&get_renderer(renderer_id)?, "Pause", &[]).await.map(|_| ())
+}
+
+/// 继续播放
+pub async fn resume(renderer_id: &str) -> Result<(), String> {
+    av_transport(&get_renderer(renderer_id)?, "Play", &[("Speed", "1".to_string())]).await.map(|_| ())
+}
+
+/// 停止
+pub async fn stop(renderer_id: &str) -> Result<(), String> {
+    av_transport(&get_renderer(renderer_id)?, "Stop", &[]).await.map(|_| ())
+}
+
+/// 跳转到指定位置（秒）
+pub async fn seek(renderer_id: &str, position_secs: u64) -> Result<(), String> {
+    let target = format!(
+        "{}:{:02}:{:02}",
+        position_secs / 3600,
+        position_secs / 60 % 60,
+        position_secs % 60
+    );
+    av_transport(&get_renderer(renderer_id)?, "Seek", &[
+        ("Unit", "REL_TIME".to_string()),
+        ("Target", target),
+    ])
+    .await
+    .map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paths;
+    use axum::extract::State;
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::routing::{get, post};
+    use axum::Router;
+    use std::sync::{Arc, Mutex};
+    
+    /// 模拟播放设备收到的 SOAP 请求（SOAPACTION 和请求体）
+    type Requests = Arc<Mutex<Vec<(String, String)>>>;
+    
+    /// 本地模拟的播放设备：提供设备描述和 AVTransport 控制地址，记录收到的请求，Pause 返回 UPnP 错误
+    async fn stand_in_renderer() -> (SocketAddr, Requests) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let requests = Requests::default();
+        
+        // ConnectionManager 排在前面，控制地址需要按服务类型选择，并相对于 URLBase 解析
+        let description = format!(
+            "<?xml version=\"1.0\"?><root xmlns=\"urn:schemas-upnp-org:device-1-0\">\
+             <URLBase>http://{}/base/</URLBase><device><deviceType>{}</deviceType>\
+             <friendlyName>客厅 &amp; 音箱</friendlyName><UDN>uuid:stand-in-renderer</UDN><serviceList>\
+             <service><serviceType>urn:schemas-upnp-org:service:ConnectionManager:1</serviceType>\
+             <controlURL>control/cm</controlURL></service>\
+             <service><serviceType>{}</serviceType><controlURL>control/av</controlURL></service>\
+             </serviceList></device></root>",
+            addr, MEDIA_RENDERER_TYPE, AV_TRANSPORT_TYPE
+        );
+        let app = Router::new()
+            .route("/device/description.xml", get(move || async move { description }))
+            .route("/base/control/av", post(handle_control))
+            .with_state(requests.clone());
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        (addr, requests)
+    }
+    
+    async fn handle_control(State(requests): State<Requests>, headers: HeaderMap, body: String) -> (StatusCode, String) {
+        let action = headers
+            .get("SOAPACTION")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        let fault = action.ends_with("#Pause\"");
+        requests.lock().unwrap().push((action, body));
+        if fault {
+            let fault = soap_envelope(
+                "<s:Fault><faultcode>s:Client</faultcode><faultstring>UPnPError</faultstring><detail>\
+                 <UPnPError xmlns=\"urn:schemas-upnp-org:control-1-0\"><errorCode>701</errorCode>\
+                 <errorDescription>Transition not available</errorDescription></UPnPError></detail></s:Fault>",
+            );
+            return (StatusCode::INTERNAL_SERVER_ERROR, fault);
+        }
+        (StatusCode::OK, soap_envelope(""))
+    }
+    
+    /// 取出一个动作的请求参数（解码后）
+    fn argument(body: &str, action: &str, name: &str) -> Option<String> {
+        xml_elements(body, action).first().and_then(|args| xml_text(args, name))
+    }
+    
+    #[tokio::test]
+    async fn stand_in_renderer_receives_av_transport_actions() {
+        let dir = std::env::temp_dir().join(format!("dlna-renderer-test-{}", rand::random::<u64>()));
+        paths::init_in(&dir);
+        lan::enable(Some("127.0.0.1".to_string())).await.unwrap();
+        let (addr, requests) = stand_in_renderer().await;
+        
+        // 设备描述：控制地址相对于 URLBase，而不是描述地址
+        let renderer = connect(format!("http://{}/device/description.xml", addr)).await.unwrap();
+        assert_eq!(renderer.id, "uuid:stand-in-renderer");
+        assert_eq!(renderer.name, "客厅 & 音箱");
+        assert_eq!(renderer.av_transport_url, format!("http://{}/base/control/av", addr));
+        
+        // 播放：先设置地址和元数据，再开始播放
+        let track = LanTrack {
+            bvid: "BV1xx411c7mD".to_string(),
+            cid: 42,
+            title: "报菜名 <完整版> & 返场".to_string(),
+            author: Some("德云社".to_string()),
+            ..Default::default()
+        };
+        play(&renderer.id, track).await.unwrap();
+        let url = lan::track_url("BV1xx411c7mD", 42).await.unwrap();
+        {
+            let requests = requests.lock().unwrap();
+            assert_eq!(requests.len(), 2);
+            let (action, body) = &requests[0];
+            assert_eq!(action, &format!("\"{}#SetAVTransportURI\"", AV_TRANSPORT_TYPE));
+            assert_eq!(argument(body, "SetAVTransportURI", "InstanceID").as_deref(), Some("0"));
+            assert_eq!(argument(body, "SetAVTransportURI", "CurrentURI"), Some(url.clone()));
+            // 元数据作为文本传输，DIDL 中已转义的标题会再被转义一次
+            assert!(body.contains("&lt;dc:title&gt;报菜名 &amp;lt;完整版&amp;gt; &amp;amp; 返场&lt;/dc:title&gt;"));
+            assert_eq!(
+                argument(body, "SetAVTransportURI", "CurrentURIMetaData"),
+                Some(didl_lite(&format!(
+                    "<item id=\"BV1xx411c7mD/42\" parentID=\"-1\" restricted=\"1\">\
+                     <dc:title>报菜名 &lt;完整版&gt; &amp; 返场</dc:title><dc:creator>德云社</dc:creator>\
+                     <upnp:class>object.item.audioItem.musicTrack</upnp:class>\
+                     <res protocolInfo=\"http-get:*:audio/mp4:*\">{}</res></item>",
+                    escape_html(&url)
+                )))
+            );
+            let (action, body) = &requests[1];
+            assert_eq!(action, &format!("\"{}#Play\"", AV_TRANSPORT_TYPE));
+            assert_eq!(argument(body, "Play", "Speed").as_deref(), Some("1"));
+        }
+        
+        // 跳转：REL_TIME 格式为 H:MM:SS
+        seek(&renderer.id, 3725).await.unwrap();
+        seek(&renderer.id, 59).await.unwrap();
+        {
+            let requests = requests.lock().unwrap();
+            let (action, body) = &requests[2];
+            assert_eq!(action, &format!("\"{}#Seek\"", AV_TRANSPORT_TYPE));
+            assert_eq!(argument(body, "Seek", "Unit").as_deref(), Some("REL_TIME"));
+            assert_eq!(argument(body, "Seek", "Target").as_deref(), Some("1:02:05"));
+            assert_eq!(argument(&requests[3].1, "Seek", "Target").as_deref(), Some("0:00:59"));
+        }
+        
+        // 设备返回 SOAP 错误时带上错误描述
+        let err = pause(&renderer.id).await.unwrap_err();
+        assert_eq!(err, "播放设备拒绝了 Pause 请求: Transition not available");
+        resume(&renderer.id).await.unwrap();
+        stop(&renderer.id).await.unwrap();
+        {
+            let requests = requests.lock().unwrap();
+            let actions: Vec<&str> = requests[4..]
+                .iter()
+                .map(|(action, _)| action.rsplit('#').next().unwrap())
+                .collect();
+            assert_eq!(actions, ["Pause\"", "Play\"", "Stop\""]);
+        }
+        
+        // 未知设备
+        assert!(play("uuid:missing", LanTrack::default()).await.is_err());
+        
+        lan::disable().await.unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,28 @@
+//! 前端事件模块
+//! 
+//! 保存 AppHandle，供代理等后台模块向前端发送事件
+
+use lazy_static::lazy_static;
+use serde::Serialize;
+use std::sync::RwLock;
+use tauri::Emitter;
+
+lazy_static! {
+    static ref APP_HANDLE: RwLock<Option<tauri::AppHandle>> = RwLock::new(None);
+}
+
+/// 记录 AppHandle（在 setup 阶段调用）
+pub fn init(app: &tauri::AppHandle) {
+    if let Ok(mut handle) = APP_HANDLE.write() {
+        *handle = Some(app.clone());
+    }
+}
+
+/// 向前端发送事件（应用未初始化时忽略）
+pub fn emit<S: Serialize + Clone>(event: &str, payload: S) {
+    if let Ok(handle) = APP_HANDLE.read() {
+        if let Some(app) = handle.as_ref() {
+            app.emit(event, payload).ok();
+        }
+    }
+}
@@ -1,17 +1,31 @@
 //! HTTP 客户端模块
 //! 
 //! 提供全局 HTTP 客户端管理和 B 站请求头处理
+//! 
+//! 客户端创建时获取 buvid3/buvid4 并生成 bili_ticket，之后定期检查 bili_ticket 是否将要过期，
+//! 使请求的 Cookie 与正常浏览器会话一致，降低触发风控的概率
 
-use crate::constants::{BILIBILI_ORIGIN, BILIBILI_REFERER, USER_AGENT};
+use crate::constants::{
+    BILIBILI_API_BASE, BILIBILI_ORIGIN, BILIBILI_REFERER, BILI_TICKET_CHECK_INTERVAL_SECS,
+    BILI_TICKET_HMAC_KEY, BILI_TICKET_KEY_ID, BILI_TICKET_REFRESH_MARGIN_SECS, BUVID_MAX_AGE_SECS,
+    USER_AGENT,
+};
+use crate::cookie_jar::CookieJar;
+use hmac::{Hmac, Mac};
 use lazy_static::lazy_static;
-use std::sync::Arc;
+use sha2::Sha256;
+use std::sync::{Arc, Once};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::sync::Mutex;
 
-// 全局 HTTP 客户端（带 Cookie 存储）
+// 全局 HTTP 客户端与其 Cookie 存储（登录后的 SESSDATA 等也保存在这里，持久化到磁盘）
 lazy_static! {
     static ref HTTP_CLIENT: Arc<Mutex<Option<reqwest::Client>>> = Arc::new(Mutex::new(None));
+    static ref COOKIE_JAR: Arc<CookieJar> = Arc::new(CookieJar::load());
 }
 
+static TICKET_REFRESH_TASK: Once = Once::new();
+
 /// 获取或创建 HTTP 客户端
 pub async fn get_http_client() -> Result<reqwest::Client, String> {
     let mut client_guard = HTTP_CLIENT.lock().await;
@@ -21,21 +35,165 @@ pub async fn get_http_client() -> Result<reqwest::Client, String> {
     }
     
     // 创建带 Cookie 存储的客户端
-    let client = reqwest::Client::builder()
-        .user_agent(USER_AGENT)
-        .cookie_store(true)
-        .build()
-        .map_err(|e| format!("创建 HTTP 客户端失败: {}", e))?;
+    let client = build_client(reqwest::redirect::Policy::default())?;
     
-    // 预访问 bilibili.com 获取 cookies
-    let _ = add_bilibili_headers(client.get("https://www.bilibili.com"))
-        .send()
-        .await;
+    warm_up(&client).await;
+    // 定期检查 bili_ticket（各个客户端共用同一个 Cookie 存储，任务只需启动一次）
+    TICKET_REFRESH_TASK.call_once(|| {
+        let client = client.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(BILI_TICKET_CHECK_INTERVAL_SECS));
+            // 第一次触发是立即的，创建客户端时已经检查过
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                refresh_bili_ticket_if_needed(&client).await;
+            }
+        });
+    });
     
     *client_guard = Some(client.clone());
     Ok(client)
 }
 
+/// 创建共用 Cookie 存储的客户端（重定向策略由调用方指定）
+pub fn build_client(redirect: reqwest::redirect::Policy) -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .user_agent(USER_AGENT)
+        .cookie_provider(cookie_jar())
+        .redirect(redirect)
+        .build()
+        .map_err(|e| format!("创建 HTTP 客户端失败: {}", e))
+}
+
+/// 预热 Cookie：补全 buvid3/buvid4（已保存时跳过），检查 bili_ticket
+async fn warm_up(client: &reqwest::Client) {
+    if cookie_value("buvid3").is_none() || cookie_value("buvid4").is_none() {
+        if let Err(e) = fetch_buvid(client).await {
+            eprintln!("[HTTP] 获取 buvid 失败: {}", e);
+            // 退回到预访问首页，由服务器下发 buvid3
+            let _ = add_bilibili_headers(client.get(BILIBILI_REFERER))
+                .send()
+                .await;
+        }
+    }
+    refresh_bili_ticket_if_needed(client).await;
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// 写入 .bilibili.com 域的 Cookie
+fn set_bilibili_cookie(name: &str, value: &str, max_age_secs: u64) {
+    if let Ok(url) = reqwest::Url::parse(BILIBILI_REFERER) {
+        COOKIE_JAR.add_cookie_str(
+            &format!("{}={}; Domain=.bilibili.com; Path=/; Max-Age={}", name, value, max_age_secs),
+            &url,
+        );
+    }
+}
+
+/// 请求 JSON 接口并检查 code
+async fn request_json(request: reqwest::RequestBuilder) -> Result<serde_json::Value, String> {
+    let body = add_bilibili_headers(request)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?
+        .text()
+        .await
+        .map_err(|e| e.to_string())?;
+    let json: serde_json::Value = serde_json::from_str(&body).map_err(|e| e.to_string())?;
+    if json["code"].as_i64() != Some(0) {
+        return Err(format!("接口返回错误 ({})", json["code"]));
+    }
+    Ok(json)
+}
+
+/// 通过 spi 接口获取 buvid3/buvid4
+async fn fetch_buvid(client: &reqwest::Client) -> Result<(), String> {
+    let json = request_json(client.get(format!("{}/x/frontend/finger/spi", BILIBILI_API_BASE))).await?;
+    let data = &json["data"];
+    match (data["b_3"].as_str(), data["b_4"].as_str()) {
+        (Some(buvid3), Some(buvid4)) => {
+            set_bilibili_cookie("buvid3", buvid3, BUVID_MAX_AGE_SECS);
+            set_bilibili_cookie("buvid4", &urlencoding::encode(buvid4), BUVID_MAX_AGE_SECS);
+            Ok(())
+        }
+        _ => Err("响应中缺少 buvid".to_string()),
+    }
+}
+
+/// bili_ticket 不存在或即将过期时重新生成
+async fn refresh_bili_ticket_if_needed(client: &reqwest::Client) {
+    let expires = cookie_value("bili_ticket_expires").and_then(|v| v.parse::<u64>().ok());
+    if cookie_value("bili_ticket").is_some()
+        && expires.map(|e| e > unix_now() + BILI_TICKET_REFRESH_MARGIN_SECS).unwrap_or(false)
+    {
+        return;
+    }
+    if let Err(e) = generate_bili_ticket(client).await {
+        eprintln!("[HTTP] 生成 bili_ticket 失败: {}", e);
+    }
+}
+
+/// 生成 bili_ticket（请求参数使用 HMAC-SHA256 签名）
+async fn generate_bili_ticket(client: &reqwest::Client) -> Result<(), String> {
+    let ts = unix_now().to_string();
+    let mut mac = Hmac::<Sha256>::new_from_slice(BILI_TICKET_HMAC_KEY.as_bytes())
+        .map_err(|e| e.to_string())?;
+    mac.update(format!("ts{}", ts).as_bytes());
+    let hexsign: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    
+    let csrf = cookie_value("bili_jct").unwrap_or_default();
+    let json = request_json(
+        client
+            .post(format!(
+                "{}/bapis/bilibili.api.ticket.v1.Ticket/GenWebTicket",
+                BILIBILI_API_BASE
+            ))
+            .query(&[
+                ("key_id", BILI_TICKET_KEY_ID),
+                ("hexsign", hexsign.as_str()),
+                ("context[ts]", ts.as_str()),
+                ("csrf", csrf.as_str()),
+            ]),
+    )
+    .await?;
+    
+    let data = &json["data"];
+    let ticket = data["ticket"].as_str().ok_or_else(|| "响应中缺少 ticket".to_string())?;
+    let created_at = data["created_at"].as_u64().unwrap_or_else(unix_now);
+    let ttl = data["ttl"].as_u64().unwrap_or(0);
+    let expires = created_at + ttl;
+    let max_age = expires.saturating_sub(unix_now());
+    set_bilibili_cookie("bili_ticket", ticket, max_age);
+    set_bilibili_cookie("bili_ticket_expires", &expires.to_string(), max_age);
+    Ok(())
+}
+
+/// 重新获取 buvid 和 bili_ticket（触发风控后调用，已保存的也会替换）
+pub async fn rewarm_cookies() {
+    let client = match get_http_client().await {
+        Ok(client) => client,
+        Err(_) => return,
+    };
+    if let Err(e) = fetch_buvid(&client).await {
+        eprintln!("[HTTP] 获取 buvid 失败: {}", e);
+    }
+    if let Err(e) = generate_bili_ticket(&client).await {
+        eprintln!("[HTTP] 生成 bili_ticket 失败: {}", e);
+    }
+}
+
 /// 为请求添加 B 站标准请求头
 pub fn add_bilibili_headers(request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
     request
@@ -43,3 +201,21 @@ pub fn add_bilibili_headers(request: reqwest::RequestBuilder) -> reqwest::Reques
         .header("Origin", BILIBILI_ORIGIN)
         .header("User-Agent", USER_AGENT)
 }
+
+/// 获取 Cookie 存储
+pub fn cookie_jar() -> Arc<CookieJar> {
+    COOKIE_JAR.clone()
+}
+
+/// 读取发送给 bilibili.com 的 Cookie 值
+pub fn cookie_value(name: &str) -> Option<String> {
+    let url = reqwest::Url::parse(BILIBILI_REFERER).ok()?;
+    COOKIE_JAR.value(&url, name)
+}
+
+/// 清空 Cookie（包括磁盘上保存的）并重建 HTTP 客户端，下次请求时重新预访问
+pub async fn clear_cookies() {
+    let mut client_guard = HTTP_CLIENT.lock().await;
+    COOKIE_JAR.clear();
+    *client_guard = None;
+}
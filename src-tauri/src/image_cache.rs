@@ -0,0 +1,161 @@
+//! 封面图片缓存模块
+//! 
+//! 负责封面图片的缩放、重新编码和磁盘缓存
+
+use crate::constants::{IMAGE_CACHE_MAX_FILES, IMAGE_JPEG_QUALITY, IMAGE_MAX_DIMENSION};
+use crate::paths;
+use image::imageops::FilterType;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 处理后的图片
+pub struct CachedImage {
+    pub data: Vec<u8>,
+    pub content_type: String,
+}
+
+/// 目标尺寸（宽、高均可选，都未指定时不缩放）
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ImageSize {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+impl ImageSize {
+    /// 限制尺寸范围，0 视为未指定
+    pub fn clamped(width: Option<u32>, height: Option<u32>) -> Self {
+        let clamp = |v: Option<u32>| v.filter(|v| *v > 0).map(|v| v.min(IMAGE_MAX_DIMENSION));
+        Self {
+            width: clamp(width),
+            height: clamp(height),
+        }
+    }
+    
+    pub fn is_resize(&self) -> bool {
+        self.width.is_some() || self.height.is_some()
+    }
+}
+
+/// 去掉 B 站图片服务的处理后缀（如 `@300w_300h_1c.webp`），获取原图地址
+pub fn original_image_url(url: &str) -> String {
+    let (base, query) = match url.split_once('?') {
+        Some((base, query)) => (base, Some(query)),
+        None => (url, None),
+    };
+    let file_start = base.rfind('/').map(|i| i + 1).unwrap_or(0);
+    let base = match base[file_start..].find('@') {
+        Some(at) => &base[..file_start + at],
+        None => base,
+    };
+    match query {
+        Some(query) => format!("{}?{}", base, query),
+        None => base.to_string(),
+    }
+}
+
+/// 缓存文件使用的扩展名（读取时按此顺序查找，缩放后的图片都是 jpg）
+const CACHE_EXTENSIONS: &[&str] = &["jpg", "webp", "png", "gif", "avif", "bin"];
+
+/// 缓存文件路径（不含扩展名，文件名为地址和尺寸的 SHA-256，不随编译器版本变化）
+fn cache_path(url: &str, size: ImageSize) -> Result<PathBuf, String> {
+    let key = format!("{}#{}x{}", url, size.width.unwrap_or(0), size.height.unwrap_or(0));
+    let digest = Sha256::digest(key.as_bytes());
+    Ok(paths::cache_dir("images")?.join(format!("{:x}", digest)))
+}
+
+/// 根据扩展名推断内容类型
+fn content_type_for(ext: &str) -> &'static str {
+    match ext {
+        "jpg" => "image/jpeg",
+        "png" => "image/png",
+        "webp" => "image/webp",
+        "gif" => "image/gif",
+        "avif" => "image/avif",
+        _ => "application/octet-stream",
+    }
+}
+
+/// 根据内容类型推断扩展名
+fn ext_for(content_type: &str) -> &'static str {
+    match content_type.split(';').next().unwrap_or("").trim() {
+        "image/jpeg" => "jpg",
+        "image/png" => "png",
+        "image/webp" => "webp",
+        "image/gif" => "gif",
+        "image/avif" => "avif",
+        _ => "bin",
+    }
+}
+
+/// 读取磁盘缓存（按扩展名直接查找文件）
+pub fn load(url: &str, size: ImageSize) -> Option<CachedImage> {
+    let base = cache_path(url, size).ok()?;
+    CACHE_EXTENSIONS.iter().find_map(|ext| {
+        let data = fs::read(base.with_extension(ext)).ok()?;
+        Some(CachedImage {
+            data,
+            content_type: content_type_for(ext).to_string(),
+        })
+    })
+}
+
+/// 写入磁盘缓存（同一封面的并发写入使用各自的临时文件）
+pub fn store(url: &str, size: ImageSize, image: &CachedImage) -> Result<(), String> {
+    let base = cache_path(url, size)?;
+    let path = base.with_extension(ext_for(&image.content_type));
+    paths::write_atomic(&path, &image.data)?;
+    
+    if let Some(dir) = path.parent() {
+        prune(dir);
+    }
+    Ok(())
+}
+
+/// 缓存文件超过上限时删除最旧的文件
+fn prune(dir: &Path) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut files: Vec<_> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let modified = entry.metadata().ok()?.modified().ok()?;
+            Some((modified, entry.path()))
+        })
+        .collect();
+    if files.len() <= IMAGE_CACHE_MAX_FILES {
+        return;
+    }
+    files.sort_by_key(|(modified, _)| *modified);
+    let excess = files.len() - IMAGE_CACHE_MAX_FILES;
+    for (_, path) in files.into_iter().take(excess) {
+        let _ = fs::remove_file(path);
+    }
+}
+
+/// 缩放并重新编码为 JPEG
+///
+/// 同时指定宽高时按比例缩放后居中裁剪（与 B 站图片服务的 `_1c` 一致），
+/// 只指定一边时按比例缩放
+pub fn resize(data: &[u8], size: ImageSize) -> Result<CachedImage, String> {
+    let img = image::load_from_memory(data).map_err(|e| format!("图片解码失败: {}", e))?;
+    let resized = match (size.width, size.height) {
+        (Some(w), Some(h)) => img.resize_to_fill(w, h, FilterType::Lanczos3),
+        (Some(w), None) => img.resize(w, u32::MAX, FilterType::Lanczos3),
+        (None, Some(h)) => img.resize(u32::MAX, h, FilterType::Lanczos3),
+        (None, None) => img,
+    };
+    
+    let mut out = Vec::new();
+    let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut out, IMAGE_JPEG_QUALITY);
+    resized
+        .to_rgb8()
+        .write_with_encoder(encoder)
+        .map_err(|e| format!("图片编码失败: {}", e))?;
+    
+    Ok(CachedImage {
+        data: out,
+        content_type: "image/jpeg".to_string(),
+    })
+}
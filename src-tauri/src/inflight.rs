@@ -0,0 +1,586 @@
+//! 上游请求合并模块
+//! 
+//! WebView 的媒体元素经常并发发起重叠的 Range 请求，这里让同一音频流上
+//! 区间重叠的并发请求共享一次上游传输，再把数据分发给所有等待的客户端
+
+use axum::body::Bytes;
+use axum::http::StatusCode;
+use futures::Stream;
+use lazy_static::lazy_static;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::sync::watch;
+
+use crate::constants::{INFLIGHT_BUFFER_BYTES, INFLIGHT_JOIN_LAG_BYTES};
+use crate::play_session::ThroughputProbe;
+
+/// 上游传输的区间信息（拿到响应头后确定）
+#[derive(Debug, Clone)]
+pub struct TransferMeta {
+    /// 本次传输的最后一个字节（包含）
+    pub end: u64,
+    /// 完整文件长度
+    pub total: u64,
+    pub content_type: String,
+}
+
+/// 传输状态（由上游读取任务和各个读者共享）
+struct TransferState {
+    meta: Option<TransferMeta>,
+    /// 已缓冲的数据块，第一个块从 base 偏移开始
+    chunks: VecDeque<Bytes>,
+    base: u64,
+    /// 下一个待接收字节的偏移
+    received: u64,
+    done: bool,
+    error: Option<(StatusCode, String)>,
+    /// 读者 ID -> 读者当前读取位置
+    readers: HashMap<u64, u64>,
+    next_reader_id: u64,
+}
+
+/// 一次进行中的上游传输
+pub struct InflightTransfer {
+    key: String,
+    start: u64,
+    /// 发起传输时请求的结束位置（None 表示读到文件末尾）
+    requested_end: Option<u64>,
+    state: Mutex<TransferState>,
+    /// 状态变化通知（新数据到达、读者前进或退出）
+    changed: watch::Sender<u64>,
+}
+
+// 进行中的传输（按音频流分组）
+lazy_static! {
+    static ref INFLIGHT_TRANSFERS: Mutex<HashMap<String, Vec<Arc<InflightTransfer>>>> =
+        Mutex::new(HashMap::new());
+}
+
+impl InflightTransfer {
+    fn notify(&self) {
+        self.changed.send_modify(|version| *version = version.wrapping_add(1));
+    }
+    
+    /// 判断请求区间能否加入此传输（调用方需持有状态锁）
+    fn can_join(&self, state: &TransferState, start: u64, end: Option<u64>) -> bool {
+        if state.done {
+            return false;
+        }
+        // 新读者的起点必须仍在缓冲区内，且不能比已接收位置领先太多
+        let lower = state.base.max(self.start);
+        let upper = state.received.max(self.start) + INFLIGHT_JOIN_LAG_BYTES;
+        if start < lower || start > upper {
+            return false;
+        }
+        match &state.meta {
+            Some(meta) => end.unwrap_or(meta.total.saturating_sub(1)) <= meta.end,
+            None => match (self.requested_end, end) {
+                (None, _) => true,
+                (Some(requested), Some(end)) => end <= requested,
+                (Some(_), None) => false,
+            },
+        }
+    }
+    
+    /// 请求区间可以合并时注册为读者
+    ///
+    /// 判断和注册在同一次加锁内完成，期间传输不会结束，缓冲数据也不会被丢弃
+    fn try_join(self: &Arc<Self>, start: u64, end: Option<u64>) -> Option<Reader> {
+        let mut state = self.state.lock().ok()?;
+        if !self.can_join(&state, start, end) {
+            return None;
+        }
+        let id = state.add_reader(start);
+        drop(state);
+        Some(Reader {
+            transfer: self.clone(),
+            id,
+            position: start,
+            end: u64::MAX,
+        })
+    }
+    
+    fn remove_reader(&self, id: u64) {
+        if let Ok(mut state) = self.state.lock() {
+            state.readers.remove(&id);
+            trim_consumed(&mut state);
+        }
+        self.notify();
+    }
+}
+
+impl TransferState {
+    /// 注册读者（返回读者 ID）
+    fn add_reader(&mut self, position: u64) -> u64 {
+        let id = self.next_reader_id;
+        self.next_reader_id += 1;
+        self.readers.insert(id, position);
+        id
+    }
+}
+
+/// 丢弃所有读者都已读过的数据块
+fn trim_consumed(state: &mut TransferState) {
+    let Some(&min_position) = state.readers.values().min() else {
+        return;
+    };
+    while let Some(front) = state.chunks.front() {
+        let front_end = state.base + front.len() as u64;
+        if front_end > min_position {
+            break;
+        }
+        state.base = front_end;
+        state.chunks.pop_front();
+    }
+}
+
+/// 结束传输并从传输表中移除
+///
+/// 先锁传输表再锁状态（与加入时的加锁顺序一致），保证结束后不会再有新读者加入
+fn finish_transfer(transfer: &Arc<InflightTransfer>, error: Option<(StatusCode, String)>) {
+    if let Ok(mut transfers) = INFLIGHT_TRANSFERS.lock() {
+        if let Ok(mut state) = transfer.state.lock() {
+            state.done = true;
+            state.error = error;
+        }
+        remove_transfer(&mut transfers, transfer);
+    }
+    transfer.notify();
+}
+
+/// 从传输表中移除传输（调用方需持有传输表锁）
+fn remove_transfer(
+    transfers: &mut HashMap<String, Vec<Arc<InflightTransfer>>>,
+    transfer: &Arc<InflightTransfer>,
+) {
+    if let Some(list) = transfers.get_mut(&transfer.key) {
+        list.retain(|t| !Arc::ptr_eq(t, transfer));
+        if list.is_empty() {
+            transfers.remove(&transfer.key);
+        }
+    }
+}
+
+/// 订阅音频流的区间 [start, end]：有可合并的进行中传输时直接加入，否则发起新传输
+///
+/// `fetch` 用于发起上游请求，参数为 Range 请求头；`probe` 只在发起新传输时用于统计上游速度
+pub async fn subscribe<F, Fut>(
+    key: String,
+    start: u64,
+    end: Option<u64>,
+    probe: Option<ThroughputProbe>,
+    fetch: F,
+) -> Result<(TransferMeta, u64, impl Stream<Item = Result<Bytes, std::io::Error>>), (StatusCode, String)>
+where
+    F: FnOnce(String) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<reqwest::Response, (StatusCode, String)>> + Send + 'static,
+{
+    let mut reader = join_or_start(key, start, end, probe, fetch)?;
+    
+    // 等待上游响应头
+    let meta = {
+        let mut changed = reader.transfer.changed.subscribe();
+        loop {
+            changed.borrow_and_update();
+            {
+                let state = reader.transfer.state.lock()
+                    .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "传输状态异常".to_string()))?;
+                if let Some(meta) = &state.meta {
+                    break meta.clone();
+                }
+                if let Some(error) = &state.error {
+                    return Err(error.clone());
+                }
+            }
+            if changed.changed().await.is_err() {
+                return Err((StatusCode::BAD_GATEWAY, "上游传输已中断".to_string()));
+            }
+        }
+    };
+    
+    let last_byte = end
+        .unwrap_or(meta.total.saturating_sub(1))
+        .min(meta.end);
+    if start > last_byte {
+        return Err((StatusCode::RANGE_NOT_SATISFIABLE, "请求区间超出文件长度".to_string()));
+    }
+    reader.end = last_byte;
+    
+    let stream = futures::stream::unfold(reader, |mut reader| async move {
+        reader.next_chunk().await.map(|item| (item, reader))
+    });
+    Ok((meta, last_byte, stream))
+}
+
+/// 查找可加入的传输，找不到时登记新传输并启动上游读取任务
+fn join_or_start<F, Fut>(
+    key: String,
+    start: u64,
+    end: Option<u64>,
+    probe: Option<ThroughputProbe>,
+    fetch: F,
+) -> Result<Reader, (StatusCode, String)>
+where
+    F: FnOnce(String) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<reqwest::Response, (StatusCode, String)>> + Send + 'static,
+{
+    let mut transfers = INFLIGHT_TRANSFERS.lock()
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "传输表异常".to_string()))?;
+    
+    if let Some(reader) = transfers
+        .get(&key)
+        .and_then(|list| list.iter().find_map(|transfer| transfer.try_join(start, end)))
+    {
+        return Ok(reader);
+    }
+    
+    let (changed, _) = watch::channel(0u64);
+    let transfer = Arc::new(InflightTransfer {
+        key: key.clone(),
+        start,
+        requested_end: end,
+        state: Mutex::new(TransferState {
+            meta: None,
+            chunks: VecDeque::new(),
+            base: start,
+            received: start,
+            done: false,
+            error: None,
+            // 发起传输的请求是第一个读者
+            readers: HashMap::from([(0, start)]),
+            next_reader_id: 1,
+        }),
+        changed,
+    });
+    transfers.entry(key).or_default().push(transfer.clone());
+    drop(transfers);
+    
+    let range = match end {
+        Some(end) => format!("bytes={}-{}", start, end),
+        None => format!("bytes={}-", start),
+    };
+    tokio::spawn(drive_transfer(transfer.clone(), fetch(range), probe));
+    Ok(Reader {
+        transfer,
+        id: 0,
+        position: start,
+        end: u64::MAX,
+    })
+}
+
+/// 上游读取任务：接收数据写入缓冲区，缓冲超过上限时等待最慢的读者
+///
+/// 吞吐量只按等待上游数据的时间统计，背压等待的时间反映的是客户端的读取速度
+async fn drive_transfer<Fut>(
+    transfer: Arc<InflightTransfer>,
+    response: Fut,
+    mut probe: Option<ThroughputProbe>,
+)
+where
+    Fut: Future<Output = Result<reqwest::Response, (StatusCode, String)>>,
+{
+    let finish = |error: Option<(StatusCode, String)>| finish_transfer(&transfer, error);
+    
+    let mut response = match response.await {
+        Ok(r) => r,
+        Err(e) => return finish(Some(e)),
+    };
+    
+    let Some(meta) = parse_meta(&response, transfer.start) else {
+        return finish(Some((StatusCode::BAD_GATEWAY, "无法确定上游响应区间".to_string())));
+    };
+    // 上游忽略 Range 返回完整文件时，需要跳过起点之前的数据
+    let mut skip = if response.status().as_u16() == 206 { 0 } else { transfer.start };
+    if let Ok(mut state) = transfer.state.lock() {
+        state.meta = Some(meta);
+    }
+    transfer.notify();
+    
+    let mut changed = transfer.changed.subscribe();
+    loop {
+        // 背压：缓冲区领先最慢读者太多时等待；所有读者都退出时停止传输
+        loop {
+            changed.borrow_and_update();
+            if stop_if_abandoned(&transfer) {
+                return;
+            }
+            let buffered = match transfer.state.lock() {
+                Ok(state) => state.received - state.base,
+                Err(_) => return finish(None),
+            };
+            if buffered < INFLIGHT_BUFFER_BYTES {
+                break;
+            }
+            if changed.changed().await.is_err() {
+                return finish(None);
+            }
+        }
+        
+        let waiting_since = Instant::now();
+        let mut chunk = match response.chunk().await {
+            Ok(Some(chunk)) => chunk,
+            Ok(None) => return finish(None),
+            Err(e) => return finish(Some((StatusCode::BAD_GATEWAY, e.to_string()))),
+        };
+        if let Some(probe) = probe.as_mut() {
+            probe.on_chunk(chunk.len(), waiting_since.elapsed());
+        }
+        if skip > 0 {
+            let skipped = skip.min(chunk.len() as u64);
+            chunk = chunk.slice(skipped as usize..);
+            skip -= skipped;
+            if chunk.is_empty() {
+                continue;
+            }
+        }
+        
+        if let Ok(mut state) = transfer.state.lock() {
+            state.received += chunk.len() as u64;
+            state.chunks.push_back(chunk);
+        }
+        transfer.notify();
+    }
+}
+
+/// 所有读者都已退出时结束传输（在传输表锁内检查，避免与新读者加入竞争）
+fn stop_if_abandoned(transfer: &Arc<InflightTransfer>) -> bool {
+    let Ok(mut transfers) = INFLIGHT_TRANSFERS.lock() else {
+        return true;
+    };
+    let Ok(mut state) = transfer.state.lock() else {
+        return true;
+    };
+    if !state.readers.is_empty() {
+        return false;
+    }
+    state.done = true;
+    drop(state);
+    remove_transfer(&mut transfers, transfer);
+    true
+}
+
+/// 从上游响应头解析传输区间
+fn parse_meta(response: &reqwest::Response, start: u64) -> Option<TransferMeta> {
+    let content_type = response.headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("audio/mp4")
+        .to_string();
+    
+    if response.status().as_u16() == 206 {
+        // Content-Range: bytes start-end/total
+        let content_range = response.headers()
+            .get("content-range")
+            .and_then(|v| v.to_str().ok())?;
+        let (range, total) = content_range.trim().strip_prefix("bytes ")?.split_once('/')?;
+        let (range_start, range_end) = range.split_once('-')?;
+        if range_start.parse::<u64>().ok()? != start {
+            return None;
+        }
+        Some(TransferMeta {
+            end: range_end.parse().ok()?,
+            total: total.parse().ok()?,
+            content_type,
+        })
+    } else {
+        let total = response.content_length()?;
+        Some(TransferMeta {
+            end: total.checked_sub(1)?,
+            total,
+            content_type,
+        })
+    }
+}
+
+/// 单个客户端的读取进度（释放时从传输中注销）
+struct Reader {
+    transfer: Arc<InflightTransfer>,
+    id: u64,
+    position: u64,
+    end: u64,
+}
+
+impl Reader {
+    /// 读取下一段数据（没有新数据时等待上游）
+    async fn next_chunk(&mut self) -> Option<Result<Bytes, std::io::Error>> {
+        let mut changed = self.transfer.changed.subscribe();
+        loop {
+            changed.borrow_and_update();
+            {
+                let mut state = self.transfer.state.lock().ok()?;
+                if self.position > self.end {
+                    return None;
+                }
+                if self.position < state.base {
+                    return Some(Err(std::io::Error::other("缓冲数据已被丢弃")));
+                }
+                if self.position < state.received {
+                    let mut offset = state.base;
+                    let mut found = None;
+                    for chunk in &state.chunks {
+                        let chunk_end = offset + chunk.len() as u64;
+                        if self.position < chunk_end {
+                            let from = (self.position - offset) as usize;
+                            let to = (chunk_end.min(self.end + 1) - offset) as usize;
+                            found = Some(chunk.slice(from..to));
+                            break;
+                        }
+                        offset = chunk_end;
+                    }
+                    if let Some(data) = found {
+                        self.position += data.len() as u64;
+                        state.readers.insert(self.id, self.position);
+                        trim_consumed(&mut state);
+                        drop(state);
+                        self.transfer.notify();
+                        return Some(Ok(data));
+                    }
+                }
+                if state.done {
+                    return match &state.error {
+                        Some((_, reason)) => Some(Err(std::io::Error::other(reason.clone()))),
+                        None => Some(Err(std::io::Error::other("上游数据提前结束"))),
+                    };
+                }
+            }
+            if changed.changed().await.is_err() {
+                return None;
+            }
+        }
+    }
+}
+
+impl Drop for Reader {
+    fn drop(&mut self) {
+        self.transfer.remove_reader(self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn new_transfer(start: u64, requested_end: Option<u64>) -> InflightTransfer {
+        InflightTransfer {
+            key: "test".to_string(),
+            start,
+            requested_end,
+            state: Mutex::new(TransferState {
+                meta: None,
+                chunks: VecDeque::new(),
+                base: start,
+                received: start,
+                done: false,
+                error: None,
+                readers: HashMap::new(),
+                next_reader_id: 0,
+            }),
+            changed: watch::channel(0u64).0,
+        }
+    }
+    
+    fn meta(end: u64, total: u64) -> TransferMeta {
+        TransferMeta {
+            end,
+            total,
+            content_type: "audio/mp4".to_string(),
+        }
+    }
+    
+    fn can_join(transfer: &InflightTransfer, start: u64, end: Option<u64>) -> bool {
+        transfer.can_join(&transfer.state.lock().unwrap(), start, end)
+    }
+    
+    #[test]
+    fn can_join_start_window() {
+        let transfer = new_transfer(1000, None);
+        {
+            let mut state = transfer.state.lock().unwrap();
+            state.base = 2000;
+            state.received = 5000;
+        }
+        // 起点在 [已丢弃位置, 已接收位置 + 允许领先量] 之间
+        assert!(!can_join(&transfer, 1999, None));
+        assert!(can_join(&transfer, 2000, None));
+        assert!(can_join(&transfer, 5000 + INFLIGHT_JOIN_LAG_BYTES, None));
+        assert!(!can_join(&transfer, 5001 + INFLIGHT_JOIN_LAG_BYTES, None));
+        
+        // 尚未收到数据时以传输起点为准
+        let fresh = new_transfer(1000, None);
+        assert!(!can_join(&fresh, 999, None));
+        assert!(can_join(&fresh, 1000 + INFLIGHT_JOIN_LAG_BYTES, None));
+        
+        fresh.state.lock().unwrap().done = true;
+        assert!(!can_join(&fresh, 1000, None));
+    }
+    
+    #[test]
+    fn can_join_end_before_and_after_meta() {
+        // 响应头到达前按请求的结束位置判断
+        let bounded = new_transfer(0, Some(999));
+        assert!(can_join(&bounded, 0, Some(999)));
+        assert!(!can_join(&bounded, 0, Some(1000)));
+        assert!(!can_join(&bounded, 0, None));
+        assert!(can_join(&new_transfer(0, None), 0, Some(u64::MAX)));
+        
+        // 响应头到达后按实际区间判断（读到文件末尾的请求需要传输覆盖最后一个字节）
+        bounded.state.lock().unwrap().meta = Some(meta(999, 5000));
+        assert!(can_join(&bounded, 0, Some(999)));
+        assert!(!can_join(&bounded, 0, Some(1000)));
+        assert!(!can_join(&bounded, 0, None));
+        
+        let whole = new_transfer(0, Some(999));
+        whole.state.lock().unwrap().meta = Some(meta(4999, 5000));
+        assert!(can_join(&whole, 0, None));
+        assert!(can_join(&whole, 0, Some(4999)));
+        assert!(!can_join(&whole, 0, Some(5000)));
+    }
+    
+    #[test]
+    fn trim_consumed_keeps_chunks_not_fully_read() {
+        let transfer = new_transfer(100, None);
+        let mut state = transfer.state.lock().unwrap();
+        for _ in 0..3 {
+            state.chunks.push_back(Bytes::from(vec![0u8; 10]));
+        }
+        state.received = 130;
+        
+        // 没有读者时不丢弃
+        trim_consumed(&mut state);
+        assert_eq!((state.base, state.chunks.len()), (100, 3));
+        
+        // 以最慢的读者为准，读到块末尾的块才丢弃
+        let slow = state.add_reader(109);
+        state.add_reader(125);
+        trim_consumed(&mut state);
+        assert_eq!((state.base, state.chunks.len()), (100, 3));
+        
+        state.readers.insert(slow, 110);
+        trim_consumed(&mut state);
+        assert_eq!((state.base, state.chunks.len()), (110, 2));
+        
+        state.readers.insert(slow, 130);
+        trim_consumed(&mut state);
+        assert_eq!((state.base, state.chunks.len()), (120, 1));
+        
+        state.readers.values_mut().for_each(|position| *position = 130);
+        trim_consumed(&mut state);
+        assert_eq!((state.base, state.chunks.len()), (130, 0));
+    }
+    
+    #[test]
+    fn try_join_registers_reader_once() {
+        let transfer = Arc::new(new_transfer(0, None));
+        let reader = transfer.try_join(0, None).unwrap();
+        assert_eq!(reader.position, 0);
+        assert_eq!(transfer.state.lock().unwrap().readers.len(), 1);
+        assert!(transfer.try_join(1 + INFLIGHT_JOIN_LAG_BYTES, None).is_none());
+        assert_eq!(transfer.state.lock().unwrap().readers.len(), 1);
+        
+        // 读者释放时注销
+        drop(reader);
+        assert!(transfer.state.lock().unwrap().readers.is_empty());
+    }
+}
@@ -0,0 +1,414 @@
+//! 局域网播放模块
+//! 
+//! 可选开启：在局域网地址上提供带令牌的播放页和每个分 P 的固定播放地址，
+//! 供同一网络中的其他电脑或音箱浏览器收听
+
+use crate::access_log;
+use crate::constants::{LAN_RECENT_TRACKS, LAN_STREAMING_PORT, PROXY_SHUTDOWN_TIMEOUT_SECS};
+use crate::dlna;
+use crate::library;
+use crate::persisted_token::PersistedToken;
+use crate::podcast;
+use crate::proxy;
+use axum::{
+    body::{Body, Bytes},
+    extract::{Path, Query},
+    http::{header, HeaderMap, StatusCode},
+    middleware,
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::get,
+    Router,
+};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::net::{IpAddr, SocketAddr, UdpSocket};
+use std::sync::RwLock;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio::sync::{oneshot, Mutex};
+use tokio::task::JoinHandle;
+
+/// 运行中的局域网服务句柄
+struct LanServerHandle {
+    addr: SocketAddr,
+    shutdown_tx: oneshot::Sender<()>,
+    task: JoinHandle<()>,
+}
+
+/// 局域网播放状态
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LanStatus {
+    pub enabled: bool,
+    pub address: Option<String>,
+    /// 播放页地址（含令牌）
+    pub index_url: Option<String>,
+    /// 始终指向当前曲目的地址（含令牌）
+    pub now_playing_url: Option<String>,
+    /// 播客订阅地址（含令牌）
+    pub podcast_url: Option<String>,
+}
+
+/// 局域网播放页中的曲目（字段与前端 VideoItem/FavoriteItem 一致）
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct LanTrack {
+    pub bvid: String,
+    pub cid: u64,
+    pub title: String,
+    #[serde(default)]
+    pub author: Option<String>,
+    /// 封面地址
+    #[serde(default)]
+    pub pic: Option<String>,
+    /// 时长（秒）
+    #[serde(default)]
+    pub duration: Option<u64>,
+    /// 收藏时间（毫秒时间戳）
+    #[serde(default)]
+    pub added_at: Option<u64>,
+}
+
+/// 访问令牌查询参数
+#[derive(Deserialize)]
+struct TokenQuery {
+    token: Option<String>,
+}
+
+lazy_static! {
+    static ref LAN_SERVER: Mutex<Option<LanServerHandle>> = Mutex::new(None);
+    /// 局域网访问令牌（持久化保存，曲目地址在重启后保持不变）
+    static ref LAN_TOKEN: PersistedToken = PersistedToken::new("lan_token");
+    /// 最近播放的曲目（第一项为当前曲目）
+    static ref LAN_TRACKS: RwLock<VecDeque<LanTrack>> = RwLock::new(VecDeque::new());
+}
+
+/// 获取局域网访问令牌（首次使用时从数据目录读取，不存在则生成并保存）
+pub(crate) fn lan_token() -> Result<String, String> {
+    LAN_TOKEN.get()
+}
+
+/// 重新生成局域网访问令牌（之前分享的地址全部失效）
+pub async fn regenerate_token() -> Result<LanStatus, String> {
+    LAN_TOKEN.regenerate()?;
+    Ok(status().await)
+}
+
+/// 检测本机的局域网地址（通过 UDP 连接选择出口网卡，不实际发送数据）
+pub(crate) fn detect_lan_ip() -> Result<IpAddr, String> {
+    let socket = UdpSocket::bind("0.0.0.0:0").map_err(|e| e.to_string())?;
+    socket
+        .connect("192.168.0.1:80")
+        .or_else(|_| socket.connect("8.8.8.8:80"))
+        .map_err(|e| format!("无法检测局域网地址: {}", e))?;
+    let ip = socket.local_addr().map_err(|e| e.to_string())?.ip();
+    if ip.is_unspecified() || ip.is_loopback() {
+        return Err("未连接到局域网".to_string());
+    }
+    Ok(ip)
+}
+
+/// 开启局域网播放（address 为要绑定的本机地址，不传时自动检测）
+pub async fn enable(address: Option<String>) -> Result<LanStatus, String> {
+    let mut server_guard = LAN_SERVER.lock().await;
+    if let Some(server) = server_guard.as_ref() {
+        if !server.task.is_finished() {
+            drop(server_guard);
+            return Ok(status().await);
+        }
+        *server_guard = None;
+    }
+    
+    let ip = match address.as_deref().map(str::trim).filter(|a| !a.is_empty()) {
+        Some(address) => address
+            .parse::<IpAddr>()
+            .map_err(|_| format!("无效的地址: {}", address))?,
+        None => detect_lan_ip()?,
+    };
+    lan_token()?;
+    
+    // 优先使用固定端口，方便在其他设备上收藏播放页
+    let listener = match tokio::net::TcpListener::bind(SocketAddr::new(ip, LAN_STREAMING_PORT)).await {
+        Ok(listener) => listener,
+        Err(_) => tokio::net::TcpListener::bind(SocketAddr::new(ip, 0))
+            .await
+            .map_err(|e| format!("绑定局域网地址失败: {}", e))?,
+    };
+    let addr = listener.local_addr().map_err(|e| e.to_string())?;
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    
+    let task = tokio::spawn(async move {
+        let app = Router::new()
+            .route("/", get(handle_index))
+            .route("/now", get(handle_now_playing))
+            .route(
+                "/track/:bvid/:cid",
+                get(handle_track_request).head(handle_track_head),
+            )
+            .route("/download/:name", get(handle_download).head(handle_download))
+            .merge(dlna::routes())
+            .merge(podcast::routes())
+            .layer(middleware::from_fn(access_log::middleware));
+        
+        let result = axum::serve(listener, app)
+            .with_graceful_shutdown(async {
+                let _ = shutdown_rx.await;
+            })
+            .await;
+        if let Err(err) = result {
+            eprintln!("[LAN] 服务异常退出: {:?}", err);
+        }
+    });
+    
+    *server_guard = Some(LanServerHandle {
+        addr,
+        shutdown_tx,
+        task,
+    });
+    drop(server_guard);
+    Ok(status().await)
+}
+
+/// 关闭局域网播放（同时停止 DLNA 媒体服务）
+pub async fn disable() -> Result<(), String> {
+    dlna::disable().await?;
+    
+    let server = LAN_SERVER.lock().await.take();
+    let Some(server) = server else {
+        return Ok(());
+    };
+    
+    let _ = server.shutdown_tx.send(());
+    let mut task = server.task;
+    let timeout = Duration::from_secs(PROXY_SHUTDOWN_TIMEOUT_SECS);
+    if tokio::time::timeout(timeout, &mut task).await.is_err() {
+        task.abort();
+    }
+    Ok(())
+}
+
+/// 局域网服务的监听地址（未开启时返回 None）
+pub(crate) async fn server_addr() -> Option<SocketAddr> {
+    LAN_SERVER
+        .lock()
+        .await
+        .as_ref()
+        .filter(|server| !server.task.is_finished())
+        .map(|server| server.addr)
+}
+
+/// 分 P 在局域网中的完整播放地址（需要先开启局域网播放）
+pub(crate) async fn track_url(bvid: &str, cid: u64) -> Result<String, String> {
+    let addr = server_addr().await.ok_or_else(|| "局域网播放未开启".to_string())?;
+    let track = LanTrack {
+        bvid: bvid.to_string(),
+        cid,
+        ..Default::default()
+    };
+    Ok(format!("http://{}{}", addr, track_path(&track, &lan_token()?)))
+}
+
+/// 获取局域网播放状态
+pub async fn status() -> LanStatus {
+    match (server_addr().await, lan_token()) {
+        (Some(addr), Ok(token)) => LanStatus {
+            enabled: true,
+            address: Some(addr.to_string()),
+            index_url: Some(format!("http://{}/?token={}", addr, token)),
+            now_playing_url: Some(format!("http://{}/now?token={}", addr, token)),
+            podcast_url: Some(podcast::feed_url(addr, &token)),
+        },
+        _ => LanStatus {
+            enabled: false,
+            address: None,
+            index_url: None,
+            now_playing_url: None,
+            podcast_url: None,
+        },
+    }
+}
+
+/// 更新当前曲目（同时记入最近播放列表）
+pub fn set_now_playing(track: LanTrack) {
+    if let Ok(mut tracks) = LAN_TRACKS.write() {
+        tracks.retain(|t| !(t.bvid == track.bvid && t.cid == track.cid));
+        tracks.push_front(track);
+        tracks.truncate(LAN_RECENT_TRACKS);
+    }
+}
+
+/// 校验局域网访问令牌
+pub(crate) fn is_valid_token(token: &str) -> bool {
+    LAN_TOKEN.is_valid(token)
+}
+
+/// 校验查询参数中的令牌
+fn check_token(query: &TokenQuery) -> Result<String, (StatusCode, String)> {
+    let expected = lan_token().map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    match query.token.as_deref() {
+        Some(token) if proxy::tokens_match(&expected, token) => Ok(expected),
+        _ => Err((StatusCode::FORBIDDEN, "访问令牌无效".to_string())),
+    }
+}
+
+/// BV 号只包含字母和数字
+pub(crate) fn check_bvid(bvid: &str) -> Result<(), (StatusCode, String)> {
+    if bvid.is_empty() || !bvid.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err((StatusCode::BAD_REQUEST, "无效的 BV 号".to_string()));
+    }
+    Ok(())
+}
+
+/// 曲目的固定播放地址（相对路径）
+fn track_path(track: &LanTrack, token: &str) -> String {
+    format!("/track/{}/{}?token={}", track.bvid, track.cid, token)
+}
+
+/// 转义 HTML/XML 文本
+pub(crate) fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// 播放页：当前曲目播放器和最近播放列表
+async fn handle_index(Query(query): Query<TokenQuery>) -> Result<Html<String>, (StatusCode, String)> {
+    let token = check_token(&query)?;
+    let tracks: Vec<LanTrack> = LAN_TRACKS
+        .read()
+        .map(|tracks| tracks.iter().cloned().collect())
+        .unwrap_or_default();
+    
+    let now_playing = match tracks.first() {
+        Some(track) => format!(
+            "<h2>{}</h2><p>{}</p><audio controls autoplay preload=\"auto\" src=\"{}\"></audio>",
+            escape_html(&track.title),
+            escape_html(track.author.as_deref().unwrap_or("")),
+            escape_html(&track_path(track, &token))
+        ),
+        None => "<p>当前没有播放的曲目</p>".to_string(),
+    };
+    let list: String = tracks
+        .iter()
+        .map(|track| format!(
+            "<li><a href=\"{}\">{}</a></li>",
+            escape_html(&track_path(track, &token)),
+            escape_html(&track.title)
+        ))
+        .collect();
+    
+    Ok(Html(format!(
+        "<!DOCTYPE html><html lang=\"zh-CN\"><head><meta charset=\"utf-8\">\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\
+         <title>纲一下 · 局域网播放</title>\
+         <style>body{{font-family:sans-serif;max-width:640px;margin:2em auto;padding:0 1em}}\
+         audio{{width:100%}}li{{margin:.4em 0}}</style></head>\
+         <body><h1>纲一下</h1>{}<h3>最近播放</h3><ol>{}</ol></body></html>",
+        now_playing, list
+    )))
+}
+
+/// 跳转到当前曲目的播放地址
+async fn handle_now_playing(Query(query): Query<TokenQuery>) -> Result<Response, (StatusCode, String)> {
+    let token = check_token(&query)?;
+    let current = LAN_TRACKS.read().ok().and_then(|tracks| tracks.front().cloned());
+    match current {
+        Some(track) => Ok(Redirect::temporary(&track_path(&track, &token)).into_response()),
+        None => Err((StatusCode::NOT_FOUND, "当前没有播放的曲目".to_string())),
+    }
+}
+
+/// 播放指定分 P（地址只由 bvid 和 cid 决定，B 站音频地址过期后仍可使用）
+async fn handle_track_request(
+    Path((bvid, cid)): Path<(String, u64)>,
+    Query(query): Query<TokenQuery>,
+    headers: HeaderMap,
+) -> Result<Response<Body>, (StatusCode, String)> {
+    check_token(&query)?;
+    check_bvid(&bvid)?;
+    let range = headers
+        .get(header::RANGE)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string());
+    proxy::serve_track(&bvid, cid, None, range).await
+}
+
+/// 返回指定分 P 的长度和类型
+async fn handle_track_head(
+    Path((bvid, cid)): Path<(String, u64)>,
+    Query(query): Query<TokenQuery>,
+    headers: HeaderMap,
+) -> Result<Response<Body>, (StatusCode, String)> {
+    check_token(&query)?;
+    check_bvid(&bvid)?;
+    let range = headers
+        .get(header::RANGE)
+        .and_then(|v| v.to_str().ok());
+    proxy::serve_track_head(&bvid, cid, None, range).await
+}
+
+/// 提供下载目录中的文件（支持 Range 请求）
+async fn handle_download(
+    Path(name): Path<String>,
+    Query(query): Query<TokenQuery>,
+    headers: HeaderMap,
+) -> Result<Response<Body>, (StatusCode, String)> {
+    check_token(&query)?;
+    serve_download(&name, &headers).await
+}
+
+/// 发送下载目录中的文件（支持单区间 Range 请求，调用方负责校验令牌）
+pub(crate) async fn serve_download(
+    name: &str,
+    headers: &HeaderMap,
+) -> Result<Response<Body>, (StatusCode, String)> {
+    let path = library::download_path(name)
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "文件不存在".to_string()))?;
+    
+    let mut file = tokio::fs::File::open(&path)
+        .await
+        .map_err(|e| (StatusCode::NOT_FOUND, e.to_string()))?;
+    let total = file
+        .metadata()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .len();
+    let Some(last_byte) = total.checked_sub(1) else {
+        return Ok(Response::new(Body::empty()));
+    };
+    
+    let range = headers.get(header::RANGE).and_then(|v| v.to_str().ok());
+    let (start, end) = match range.and_then(proxy::parse_range) {
+        Some((start, end)) => (start, end.unwrap_or(last_byte).min(last_byte)),
+        None => (0, last_byte),
+    };
+    if start > end {
+        return Err((StatusCode::RANGE_NOT_SATISFIABLE, "请求区间无效".to_string()));
+    }
+    
+    file.seek(std::io::SeekFrom::Start(start))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let remaining = end - start + 1;
+    let stream = futures::stream::unfold((file, remaining), |(mut file, remaining)| async move {
+        if remaining == 0 {
+            return None;
+        }
+        let mut buf = vec![0u8; remaining.min(64 * 1024) as usize];
+        match file.read(&mut buf).await {
+            Ok(0) => None,
+            Ok(n) => {
+                buf.truncate(n);
+                Some((Ok::<_, std::io::Error>(Bytes::from(buf)), (file, remaining - n as u64)))
+            }
+            Err(e) => Some((Err(e), (file, 0))),
+        }
+    });
+    
+    proxy::range_response_builder(library::media_mime(name), start, end, total, range.is_some())
+        .body(Body::from_stream(stream))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
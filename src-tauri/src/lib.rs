@@ -2,11 +2,36 @@
 //! 
 //! 包含应用构建逻辑，支持桌面和移动平台
 
+mod access_log;
+mod api_cache;
+mod audio_cache;
+mod bilibili;
 mod commands;
 mod constants;
+mod cookie_jar;
+mod dlna;
+mod dlna_renderer;
 mod error;
+mod events;
 mod http_client;
+mod image_cache;
+mod inflight;
+mod lan;
+mod library;
+mod login;
+mod metrics;
+mod paths;
+mod persisted_token;
+mod pick_filter;
+mod play_session;
+mod playlist;
+mod podcast;
 mod proxy;
+mod proxy_config;
+mod random_pick;
+mod stations;
+mod risk_control;
+mod wbi;
 
 #[cfg(desktop)]
 use tauri::Manager;
@@ -23,67 +48,20 @@ pub fn run() {
     // Android 平台：代理服务器会在首次调用 proxy_audio 时自动启动
     // 不需要预启动，避免 Tokio runtime 初始化问题
     
+    let builder = builder.setup(|app| {
+        // 初始化应用目录（缓存和持久化数据）
+        paths::init(app.handle())?;
+        events::init(app.handle());
+        
+        #[cfg(desktop)]
+        setup_tray(app)?;
+        
+        Ok(())
+    });
+    
     #[cfg(desktop)]
     let builder = {
         let builder = builder.manage(CloseActionState::new());
-        let builder = builder.setup(|app| {
-            // 创建系统托盘菜单项（仅桌面平台）
-            let show_item = tauri::menu::MenuItem::with_id(app, "show", "显示", true, None::<&str>)?;
-            let hide_item = tauri::menu::MenuItem::with_id(app, "hide", "隐藏", true, None::<&str>)?;
-            let quit_item = tauri::menu::MenuItem::with_id(app, "quit", "退出", true, None::<&str>)?;
-            
-            // 创建菜单
-            let menu = tauri::menu::Menu::with_items(app, &[
-                &show_item,
-                &hide_item,
-                &quit_item,
-            ])?;
-            
-            // 创建系统托盘图标
-            let mut tray_builder = tauri::tray::TrayIconBuilder::with_id("main_tray");
-            
-            // 如果应用有默认图标，则使用它
-            if let Some(icon) = app.default_window_icon() {
-                tray_builder = tray_builder.icon(icon.clone());
-            }
-            
-            let _tray = tray_builder
-                .menu(&menu)
-                .tooltip("纲一下")
-                .on_menu_event(|app, event| {
-                    if let Some(window) = app.get_webview_window("main") {
-                        match event.id.as_ref() {
-                            "show" => {
-                                let _ = window.show();
-                                let _ = window.set_focus();
-                            }
-                            "hide" => {
-                                let _ = window.hide();
-                            }
-                            "quit" => {
-                                app.exit(0);
-                            }
-                            _ => {}
-                        }
-                    }
-                })
-                .on_tray_icon_event(|tray, event| {
-                    if let tauri::tray::TrayIconEvent::Click { button, .. } = event {
-                        if button == tauri::tray::MouseButton::Left {
-                            let app = tray.app_handle();
-                            if let Some(window) = app.get_webview_window("main") {
-                                // 点击托盘图标时，显示窗口并聚焦
-                                let _ = window.show();
-                                let _ = window.set_focus();
-                                let _ = window.unminimize();
-                            }
-                        }
-                    }
-                })
-                .build(app)?;
-            
-            Ok(())
-        });
         
         builder.on_window_event(|app, event| {
             if let tauri::WindowEvent::CloseRequested { api, .. } = event {
@@ -115,11 +93,123 @@ pub fn run() {
             commands::download_update,
             commands::install_update,
             commands::http_request,
+            commands::bilibili_search,
+            commands::bilibili_video_info,
+            commands::bilibili_play_url,
+            commands::bilibili_video_status,
+            commands::random_pick,
+            commands::clear_played_history,
+            commands::get_pick_filter,
+            commands::set_pick_filter,
+            commands::list_stations,
+            commands::select_station,
+            commands::reload_stations,
+            commands::get_risk_control_status,
+            commands::set_risk_control_rewarm,
+            commands::generate_login_qr,
+            commands::poll_login_qr,
+            commands::get_login_status,
+            commands::logout,
+            commands::clear_cookies,
+            commands::clear_api_cache,
             commands::proxy_audio,
+            commands::prefetch_audio,
+            commands::proxy_image,
+            commands::proxy_play_url,
+            commands::proxy_playlist_url,
+            commands::export_playlist,
+            commands::enable_lan_streaming,
+            commands::disable_lan_streaming,
+            commands::lan_streaming_status,
+            commands::regenerate_lan_token,
+            commands::regenerate_playlist_token,
+            commands::set_lan_now_playing,
+            commands::enable_dlna,
+            commands::disable_dlna,
+            commands::dlna_status,
+            commands::set_media_library,
+            commands::discover_renderers,
+            commands::connect_renderer,
+            commands::renderer_play,
+            commands::renderer_pause,
+            commands::renderer_resume,
+            commands::renderer_stop,
+            commands::renderer_seek,
             commands::start_proxy_server,
+            commands::stop_proxy_server,
+            commands::restart_proxy_server,
+            commands::proxy_status,
+            commands::get_proxy_config,
+            commands::set_proxy_config,
+            commands::proxy_metrics,
+            commands::query_proxy_access_log,
+            commands::clear_proxy_access_log,
+            commands::get_proxy_allowed_hosts,
+            commands::set_proxy_allowed_hosts,
             commands::set_close_action,
             commands::get_close_action,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+/// 创建系统托盘（仅桌面平台）
+#[cfg(desktop)]
+fn setup_tray(app: &mut tauri::App) -> tauri::Result<()> {
+    // 创建系统托盘菜单项（仅桌面平台）
+    let show_item = tauri::menu::MenuItem::with_id(app, "show", "显示", true, None::<&str>)?;
+    let hide_item = tauri::menu::MenuItem::with_id(app, "hide", "隐藏", true, None::<&str>)?;
+    let quit_item = tauri::menu::MenuItem::with_id(app, "quit", "退出", true, None::<&str>)?;
+    
+    // 创建菜单
+    let menu = tauri::menu::Menu::with_items(app, &[
+        &show_item,
+        &hide_item,
+        &quit_item,
+    ])?;
+    
+    // 创建系统托盘图标
+    let mut tray_builder = tauri::tray::TrayIconBuilder::with_id("main_tray");
+    
+    // 如果应用有默认图标，则使用它
+    if let Some(icon) = app.default_window_icon() {
+        tray_builder = tray_builder.icon(icon.clone());
+    }
+    
+    let _tray = tray_builder
+        .menu(&menu)
+        .tooltip("纲一下")
+        .on_menu_event(|app, event| {
+            if let Some(window) = app.get_webview_window("main") {
+                match event.id.as_ref() {
+                    "show" => {
+                        let _ = window.show();
+                        let _ = window.set_focus();
+                    }
+                    "hide" => {
+                        let _ = window.hide();
+                    }
+                    "quit" => {
+                        app.exit(0);
+                    }
+                    _ => {}
+                }
+            }
+        })
+        .on_tray_icon_event(|tray, event| {
+            if let tauri::tray::TrayIconEvent::Click { button, .. } = event {
+                if button == tauri::tray::MouseButton::Left {
+                    let app = tray.app_handle();
+                    if let Some(window) = app.get_webview_window("main") {
+                        // 点击托盘图标时，显示窗口并聚焦
+                        let _ = window.show();
+                        let _ = window.set_focus();
+                        let _ = window.unminimize();
+                    }
+                }
+            }
+        })
+        .build(app)?;
+    
+    Ok(())
+}
@@ -0,0 +1,102 @@
+//! 媒体库模块
+//! 
+//! 保存前端同步的收藏列表和下载目录，供 DLNA 媒体服务和播客订阅使用
+
+use crate::lan::LanTrack;
+use lazy_static::lazy_static;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::RwLock;
+use std::time::SystemTime;
+
+/// 可对外提供的下载文件扩展名
+const MEDIA_EXTENSIONS: &[&str] = &["m4a", "mp4", "mp3", "flac"];
+
+/// 媒体库（收藏由前端同步，下载从下载目录读取）
+#[derive(Debug, Default)]
+struct MediaLibrary {
+    favorites: Vec<LanTrack>,
+    download_dir: Option<PathBuf>,
+}
+
+/// 下载目录中的文件
+#[derive(Debug, Clone, PartialEq, Hash)]
+pub struct DownloadFile {
+    pub name: String,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+}
+
+lazy_static! {
+    static ref MEDIA_LIBRARY: RwLock<MediaLibrary> = RwLock::new(MediaLibrary::default());
+}
+
+/// 媒体库变更序号（每次同步收藏或下载目录时递增）
+static LIBRARY_REVISION: AtomicU32 = AtomicU32::new(1);
+
+/// 更新媒体库
+pub fn set_library(favorites: Vec<LanTrack>, download_dir: Option<String>) {
+    if let Ok(mut library) = MEDIA_LIBRARY.write() {
+        library.favorites = favorites;
+        library.download_dir = download_dir
+            .filter(|dir| !dir.trim().is_empty())
+            .map(PathBuf::from);
+    }
+    LIBRARY_REVISION.fetch_add(1, Ordering::SeqCst);
+}
+
+/// 媒体库变更序号
+pub fn revision() -> u32 {
+    LIBRARY_REVISION.load(Ordering::SeqCst)
+}
+
+/// 收藏列表
+pub fn favorites() -> Vec<LanTrack> {
+    MEDIA_LIBRARY.read().map(|l| l.favorites.clone()).unwrap_or_default()
+}
+
+/// 根据扩展名推断媒体类型
+pub fn media_mime(name: &str) -> &'static str {
+    match name.rsplit('.').next().unwrap_or("").to_ascii_lowercase().as_str() {
+        "mp4" => "video/mp4",
+        "mp3" => "audio/mpeg",
+        "flac" => "audio/flac",
+        _ => "audio/mp4",
+    }
+}
+
+/// 下载目录中可提供的文件（按名称排序）
+pub fn download_files() -> Vec<DownloadFile> {
+    let dir = MEDIA_LIBRARY.read().ok().and_then(|library| library.download_dir.clone());
+    let Some(entries) = dir.and_then(|dir| fs::read_dir(dir).ok()) else {
+        return Vec::new();
+    };
+    let mut files: Vec<DownloadFile> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok().filter(|m| m.is_file())?;
+            let name = entry.file_name().into_string().ok()?;
+            let ext = name.rsplit('.').next().unwrap_or("").to_ascii_lowercase();
+            if !MEDIA_EXTENSIONS.contains(&ext.as_str()) {
+                return None;
+            }
+            Some(DownloadFile {
+                name,
+                size: metadata.len(),
+                modified: metadata.modified().ok(),
+            })
+        })
+        .collect();
+    files.sort_by(|a, b| a.name.cmp(&b.name));
+    files
+}
+
+/// 下载文件的完整路径（只接受下载目录中列出的文件名，防止路径穿越）
+pub fn download_path(name: &str) -> Option<PathBuf> {
+    if !download_files().iter().any(|file| file.name == name) {
+        return None;
+    }
+    let dir = MEDIA_LIBRARY.read().ok()?.download_dir.clone()?;
+    Some(dir.join(name))
+}
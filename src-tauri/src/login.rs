@@ -0,0 +1,242 @@
+//! 扫码登录模块
+//! 
+//! 申请二维码登录密钥、生成二维码图片、轮询扫码状态，
+//! 登录成功后 SESSDATA/bili_jct 等 Cookie 保存在全局 HTTP 客户端的 Cookie 存储中
+
+use crate::constants::{BILIBILI_API_BASE, BILIBILI_PASSPORT_BASE, BILIBILI_REFERER, QR_MODULE_PIXELS};
+use crate::http_client::{add_bilibili_headers, cookie_jar, cookie_value, get_http_client, clear_cookies};
+use base64::Engine;
+use serde::Serialize;
+use std::io::Cursor;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 二维码登录信息
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct QrLogin {
+    /// 轮询扫码状态使用的密钥
+    pub qrcode_key: String,
+    /// 二维码内容
+    pub url: String,
+    /// 二维码图片（data URL）
+    pub image: String,
+}
+
+/// 扫码状态
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum QrLoginState {
+    /// 等待扫码
+    Waiting,
+    /// 已扫码，等待在手机上确认
+    Scanned,
+    /// 二维码已失效
+    Expired,
+    /// 登录成功
+    Success,
+}
+
+/// 登录状态
+#[derive(Debug, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct LoginStatus {
+    pub logged_in: bool,
+    pub mid: Option<u64>,
+    pub uname: Option<String>,
+    pub face: Option<String>,
+    /// 是否为大会员
+    pub vip: bool,
+}
+
+/// 请求 B 站 JSON 接口
+async fn get_json(url: &str, query: &[(&str, &str)]) -> Result<serde_json::Value, String> {
+    let client = get_http_client().await?;
+    let body = add_bilibili_headers(client.get(url))
+        .query(query)
+        .send()
+        .await
+        .map_err(|e| format!("请求失败: {}", e))?
+        .text()
+        .await
+        .map_err(|e| format!("读取响应失败: {}", e))?;
+    serde_json::from_str(&body).map_err(|e| format!("解析响应失败: {}", e))
+}
+
+/// 生成二维码图片的 data URL（format 为 "png" 或 "svg"，默认 svg）
+fn qr_data_url(content: &str, format: Option<&str>) -> Result<String, String> {
+    let code = qrcode::QrCode::new(content.as_bytes()).map_err(|e| format!("生成二维码失败: {}", e))?;
+    let width = code.width();
+    let colors = code.to_colors();
+    let is_dark = |x: usize, y: usize| colors[y * width + x] == qrcode::Color::Dark;
+    // 四周保留 4 个模块的空白
+    let quiet = 4;
+    let size = width + quiet * 2;
+    
+    match format.unwrap_or("svg") {
+        "svg" => {
+            let mut path = String::new();
+            for y in 0..width {
+                for x in 0..width {
+                    if is_dark(x, y) {
+                        path.push_str(&format!("M{},{}h1v1h-1z", x + quiet, y + quiet));
+                    }
+                }
+            }
+            let svg = format!(
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {size} {size}\" \
+                 width=\"{px}\" height=\"{px}\" shape-rendering=\"crispEdges\">\
+                 <rect width=\"{size}\" height=\"{size}\" fill=\"#fff\"/>\
+                 <path d=\"{path}\" fill=\"#000\"/></svg>",
+                size = size,
+                px = size as u32 * QR_MODULE_PIXELS,
+                path = path
+            );
+            Ok(format!(
+                "data:image/svg+xml;base64,{}",
+                base64::engine::general_purpose::STANDARD.encode(svg)
+            ))
+        }
+        "png" => {
+            let px = size as u32 * QR_MODULE_PIXELS;
+            let image = image::GrayImage::from_fn(px, px, |x, y| {
+                let (mx, my) = ((x / QR_MODULE_PIXELS) as usize, (y / QR_MODULE_PIXELS) as usize);
+                let dark = mx >= quiet && my >= quiet && mx < quiet + width && my < quiet + width
+                    && is_dark(mx - quiet, my - quiet);
+                image::Luma([if dark { 0 } else { 255 }])
+            });
+            let mut out = Cursor::new(Vec::new());
+            image
+                .write_to(&mut out, image::ImageFormat::Png)
+                .map_err(|e| format!("生成二维码失败: {}", e))?;
+            Ok(format!(
+                "data:image/png;base64,{}",
+                base64::engine::general_purpose::STANDARD.encode(out.into_inner())
+            ))
+        }
+        other => Err(format!("不支持的图片格式: {}", other)),
+    }
+}
+
+/// 申请二维码登录
+pub async fn generate_qr(format: Option<&str>) -> Result<QrLogin, String> {
+    let json = get_json(
+        &format!("{}/x/passport-login/web/qrcode/generate", BILIBILI_PASSPORT_BASE),
+        &[],
+    )
+    .await?;
+    let data = &json["data"];
+    let (url, qrcode_key) = match (data["url"].as_str(), data["qrcode_key"].as_str()) {
+        (Some(url), Some(key)) if json["code"].as_i64() == Some(0) => (url.to_string(), key.to_string()),
+        _ => return Err(format!("申请二维码失败 ({})", json["code"])),
+    };
+    
+    Ok(QrLogin {
+        image: qr_data_url(&url, format)?,
+        url,
+        qrcode_key,
+    })
+}
+
+/// 轮询扫码状态（登录成功时写入 Cookie）
+pub async fn poll_qr(qrcode_key: &str) -> Result<QrLoginState, String> {
+    let json = get_json(
+        &format!("{}/x/passport-login/web/qrcode/poll", BILIBILI_PASSPORT_BASE),
+        &[("qrcode_key", qrcode_key)],
+    )
+    .await?;
+    if json["code"].as_i64() != Some(0) {
+        return Err(format!("查询扫码状态失败 ({})", json["code"]));
+    }
+    
+    let data = &json["data"];
+    match data["code"].as_i64() {
+        Some(0) => {
+            // 响应已通过 Set-Cookie 写入 Cookie，这里再从跳转地址的参数中补全一次
+            if let Some(url) = data["url"].as_str() {
+                store_cookies_from_url(url);
+            }
+            if cookie_value("SESSDATA").is_none() {
+                return Err("登录成功但未获取到登录凭证".to_string());
+            }
+            Ok(QrLoginState::Success)
+        }
+        Some(86101) => Ok(QrLoginState::Waiting),
+        Some(86090) => Ok(QrLoginState::Scanned),
+        Some(86038) => Ok(QrLoginState::Expired),
+        code => Err(format!(
+            "扫码登录失败 ({}): {}",
+            code.unwrap_or(-1),
+            data["message"].as_str().unwrap_or("")
+        )),
+    }
+}
+
+/// 从登录成功的跳转地址中提取 Cookie（SESSDATA、bili_jct、DedeUserID 等）
+///
+/// 参数值保持原样（已是 Cookie 所需的编码形式），不做解码
+fn store_cookies_from_url(url: &str) {
+    let (url, site) = match (reqwest::Url::parse(url), reqwest::Url::parse(BILIBILI_REFERER)) {
+        (Ok(url), Ok(site)) => (url, site),
+        _ => return,
+    };
+    let pairs: Vec<(&str, &str)> = url
+        .query()
+        .unwrap_or("")
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .collect();
+    // Expires 为过期时间戳（秒），没有时按会话 Cookie 处理，不会保存到磁盘
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let max_age = pairs
+        .iter()
+        .find(|(name, _)| *name == "Expires")
+        .and_then(|(_, value)| value.parse::<u64>().ok())
+        .map(|expires| format!("; Max-Age={}", expires.saturating_sub(now)))
+        .unwrap_or_default();
+    
+    let jar = cookie_jar();
+    for (name, value) in pairs {
+        if matches!(name, "SESSDATA" | "bili_jct" | "DedeUserID" | "DedeUserID__ckMd5") {
+            jar.add_cookie_str(
+                &format!("{}={}; Domain=.bilibili.com; Path=/{}", name, value, max_age),
+                &site,
+            );
+        }
+    }
+}
+
+/// 获取登录状态
+pub async fn get_login_status() -> Result<LoginStatus, String> {
+    if cookie_value("SESSDATA").is_none() {
+        return Ok(LoginStatus::default());
+    }
+    let json = get_json(&format!("{}/x/web-interface/nav", BILIBILI_API_BASE), &[]).await?;
+    let data = &json["data"];
+    if json["code"].as_i64() != Some(0) || data["isLogin"].as_bool() != Some(true) {
+        return Ok(LoginStatus::default());
+    }
+    Ok(LoginStatus {
+        logged_in: true,
+        mid: data["mid"].as_u64(),
+        uname: data["uname"].as_str().map(|s| s.to_string()),
+        face: data["face"].as_str().map(|s| s.to_string()),
+        vip: data["vipStatus"].as_i64() == Some(1),
+    })
+}
+
+/// 退出登录（通知服务器注销后丢弃本地 Cookie）
+pub async fn logout() -> Result<(), String> {
+    if let Some(csrf) = cookie_value("bili_jct") {
+        let client = get_http_client().await?;
+        // 服务器注销失败不影响本地退出
+        let _ = add_bilibili_headers(client.post(format!("{}/login/exit/v2", BILIBILI_PASSPORT_BASE)))
+            .form(&[("biliCSRF", csrf)])
+            .send()
+            .await;
+    }
+    clear_cookies().await;
+    Ok(())
+}
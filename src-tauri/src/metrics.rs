@@ -0,0 +1,150 @@
+//! 代理指标模块
+//! 
+//! 统计代理服务器的流量、错误和响应延迟，供 /metrics 路由和诊断面板使用
+
+use lazy_static::lazy_static;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// 代理运行指标（进程内累计）
+#[derive(Default)]
+pub struct ProxyMetrics {
+    active_streams: AtomicU64,
+    total_requests: AtomicU64,
+    bytes_served: AtomicU64,
+    audio_cache_hits: AtomicU64,
+    audio_cache_misses: AtomicU64,
+    image_cache_hits: AtomicU64,
+    image_cache_misses: AtomicU64,
+    ttfb_total_ms: AtomicU64,
+    ttfb_samples: AtomicU64,
+    upstream_errors: Mutex<HashMap<String, u64>>,
+}
+
+/// 指标快照（序列化后返回给前端或 /metrics 路由）
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MetricsSnapshot {
+    pub uptime_secs: u64,
+    pub active_streams: u64,
+    pub total_requests: u64,
+    pub bytes_served: u64,
+    pub upstream_errors: HashMap<String, u64>,
+    /// 音频预取缓存
+    pub audio_cache_hits: u64,
+    pub audio_cache_misses: u64,
+    pub audio_cache_hit_ratio: f64,
+    /// 封面图片缓存
+    pub image_cache_hits: u64,
+    pub image_cache_misses: u64,
+    pub image_cache_hit_ratio: f64,
+    pub avg_ttfb_ms: f64,
+}
+
+lazy_static! {
+    pub static ref PROXY_METRICS: ProxyMetrics = ProxyMetrics::default();
+}
+
+/// 活跃流计数守卫（随响应流一起释放时自动减少计数）
+pub struct ActiveStreamGuard;
+
+impl Drop for ActiveStreamGuard {
+    fn drop(&mut self) {
+        PROXY_METRICS.active_streams.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// 缓存命中率（没有查询时为 0）
+fn hit_ratio(hits: u64, misses: u64) -> f64 {
+    let lookups = hits + misses;
+    if lookups > 0 {
+        hits as f64 / lookups as f64
+    } else {
+        0.0
+    }
+}
+
+impl ProxyMetrics {
+    /// 记录一次代理请求
+    pub fn record_request(&self) {
+        self.total_requests.fetch_add(1, Ordering::Relaxed);
+    }
+    
+    /// 开始一个流式响应（返回的守卫释放时结束）
+    pub fn start_stream(&self) -> ActiveStreamGuard {
+        self.active_streams.fetch_add(1, Ordering::Relaxed);
+        ActiveStreamGuard
+    }
+    
+    /// 记录发送给客户端的字节数
+    pub fn record_bytes(&self, bytes: u64) {
+        self.bytes_served.fetch_add(bytes, Ordering::Relaxed);
+    }
+    
+    /// 记录音频预取缓存命中
+    pub fn record_audio_cache_hit(&self) {
+        self.audio_cache_hits.fetch_add(1, Ordering::Relaxed);
+    }
+    
+    /// 记录音频预取缓存未命中
+    pub fn record_audio_cache_miss(&self) {
+        self.audio_cache_misses.fetch_add(1, Ordering::Relaxed);
+    }
+    
+    /// 记录封面图片缓存命中
+    pub fn record_image_cache_hit(&self) {
+        self.image_cache_hits.fetch_add(1, Ordering::Relaxed);
+    }
+    
+    /// 记录封面图片缓存未命中
+    pub fn record_image_cache_miss(&self) {
+        self.image_cache_misses.fetch_add(1, Ordering::Relaxed);
+    }
+    
+    /// 记录上游首字节耗时
+    pub fn record_ttfb(&self, elapsed: Duration) {
+        self.ttfb_total_ms.fetch_add(elapsed.as_millis() as u64, Ordering::Relaxed);
+        self.ttfb_samples.fetch_add(1, Ordering::Relaxed);
+    }
+    
+    /// 记录上游错误（key 为状态码，网络错误为 "network"）
+    pub fn record_upstream_error(&self, key: impl Into<String>) {
+        if let Ok(mut errors) = self.upstream_errors.lock() {
+            *errors.entry(key.into()).or_insert(0) += 1;
+        }
+    }
+    
+    /// 生成指标快照
+    pub fn snapshot(&self, uptime_secs: u64) -> MetricsSnapshot {
+        let audio_cache_hits = self.audio_cache_hits.load(Ordering::Relaxed);
+        let audio_cache_misses = self.audio_cache_misses.load(Ordering::Relaxed);
+        let image_cache_hits = self.image_cache_hits.load(Ordering::Relaxed);
+        let image_cache_misses = self.image_cache_misses.load(Ordering::Relaxed);
+        let ttfb_samples = self.ttfb_samples.load(Ordering::Relaxed);
+        
+        MetricsSnapshot {
+            uptime_secs,
+            active_streams: self.active_streams.load(Ordering::Relaxed),
+            total_requests: self.total_requests.load(Ordering::Relaxed),
+            bytes_served: self.bytes_served.load(Ordering::Relaxed),
+            upstream_errors: self.upstream_errors
+                .lock()
+                .map(|errors| errors.clone())
+                .unwrap_or_default(),
+            audio_cache_hits,
+            audio_cache_misses,
+            audio_cache_hit_ratio: hit_ratio(audio_cache_hits, audio_cache_misses),
+            image_cache_hits,
+            image_cache_misses,
+            image_cache_hit_ratio: hit_ratio(image_cache_hits, image_cache_misses),
+            avg_ttfb_ms: if ttfb_samples > 0 {
+                self.ttfb_total_ms.load(Ordering::Relaxed) as f64 / ttfb_samples as f64
+            } else {
+                0.0
+            },
+        }
+    }
+}
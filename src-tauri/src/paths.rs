@@ -0,0 +1,105 @@
+//! 应用目录模块
+//! 
+//! 在应用启动时记录应用目录，供代理、缓存等不持有 AppHandle 的模块使用
+
+use lazy_static::lazy_static;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+use tauri::Manager;
+
+/// 应用目录
+#[derive(Debug, Clone)]
+struct AppDirs {
+    cache_dir: PathBuf,
+    data_dir: PathBuf,
+    log_dir: PathBuf,
+}
+
+lazy_static! {
+    static ref APP_DIRS: RwLock<Option<AppDirs>> = RwLock::new(None);
+}
+
+/// 临时文件序号（同一进程内并发写入同一文件时区分临时文件）
+static TEMP_FILE_SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// 初始化应用目录（在 setup 阶段调用）
+pub fn init(app: &tauri::AppHandle) -> Result<(), String> {
+    let cache_dir = app.path()
+        .app_cache_dir()
+        .map_err(|e| format!("无法获取应用缓存目录: {}", e))?;
+    let data_dir = app.path()
+        .app_data_dir()
+        .map_err(|e| format!("无法获取应用数据目录: {}", e))?;
+    let log_dir = app.path()
+        .app_log_dir()
+        .map_err(|e| format!("无法获取应用日志目录: {}", e))?;
+    
+    let mut dirs = APP_DIRS.write().map_err(|_| "初始化应用目录失败".to_string())?;
+    *dirs = Some(AppDirs { cache_dir, data_dir, log_dir });
+    Ok(())
+}
+
+/// 使用指定目录下的子目录初始化（测试中没有 AppHandle 时使用）
+#[cfg(test)]
+pub fn init_in(dir: &std::path::Path) {
+    if let Ok(mut dirs) = APP_DIRS.write() {
+        *dirs = Some(AppDirs {
+            cache_dir: dir.join("cache"),
+            data_dir: dir.join("data"),
+            log_dir: dir.join("logs"),
+        });
+    }
+}
+
+/// 获取缓存目录下的子目录（不存在时创建）
+pub fn cache_dir(sub: &str) -> Result<PathBuf, String> {
+    let dirs = APP_DIRS.read().map_err(|_| "读取应用目录失败".to_string())?;
+    let dir = dirs.as_ref()
+        .ok_or_else(|| "应用目录未初始化".to_string())?
+        .cache_dir
+        .join(sub);
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir)
+}
+
+/// 获取持久化数据文件路径（所在目录不存在时创建）
+pub fn data_file(name: &str) -> Result<PathBuf, String> {
+    let dirs = APP_DIRS.read().map_err(|_| "读取应用目录失败".to_string())?;
+    let dir = dirs.as_ref()
+        .ok_or_else(|| "应用目录未初始化".to_string())?
+        .data_dir
+        .clone();
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir.join(name))
+}
+
+/// 获取日志文件路径（所在目录不存在时创建）
+pub fn log_file(name: &str) -> Result<PathBuf, String> {
+    let dirs = APP_DIRS.read().map_err(|_| "读取应用目录失败".to_string())?;
+    let dir = dirs.as_ref()
+        .ok_or_else(|| "应用目录未初始化".to_string())?
+        .log_dir
+        .clone();
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir.join(name))
+}
+
+/// 写入文件（先写同目录下的临时文件再重命名，避免读到写了一半的文件）
+///
+/// 临时文件名包含进程号和序号，并发写入同一文件时互不覆盖，最后完成的写入生效
+pub fn write_atomic(path: &Path, data: &[u8]) -> Result<(), String> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| format!("无效的文件路径: {}", path.display()))?
+        .to_string_lossy();
+    let seq = TEMP_FILE_SEQ.fetch_add(1, Ordering::Relaxed);
+    let tmp = path.with_file_name(format!("{}.{}.{}.tmp", name, std::process::id(), seq));
+    
+    let result = fs::write(&tmp, data).and_then(|_| fs::rename(&tmp, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result.map_err(|e| e.to_string())
+}
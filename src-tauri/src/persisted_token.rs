@@ -0,0 +1,61 @@
+//! 持久化访问令牌模块
+//! 
+//! 保存在应用数据目录中的访问令牌（局域网播放、外部播放列表使用），重启后保持不变，
+//! 可随时重新生成使旧令牌失效
+
+use crate::paths;
+use crate::proxy;
+use std::fs;
+use std::sync::RwLock;
+
+/// 持久化的访问令牌（首次使用时从数据目录读取，不存在则生成并保存）
+pub struct PersistedToken {
+    file: &'static str,
+    cached: RwLock<Option<String>>,
+}
+
+impl PersistedToken {
+    pub fn new(file: &'static str) -> Self {
+        Self {
+            file,
+            cached: RwLock::new(None),
+        }
+    }
+    
+    /// 获取令牌
+    pub fn get(&self) -> Result<String, String> {
+        if let Some(token) = self.cached.read().ok().and_then(|t| t.clone()) {
+            return Ok(token);
+        }
+        
+        let path = paths::data_file(self.file)?;
+        match fs::read_to_string(&path) {
+            Ok(token) if !token.trim().is_empty() => {
+                let token = token.trim().to_string();
+                if let Ok(mut cached) = self.cached.write() {
+                    *cached = Some(token.clone());
+                }
+                Ok(token)
+            }
+            _ => self.regenerate(),
+        }
+    }
+    
+    /// 重新生成并保存令牌（旧令牌立即失效）
+    pub fn regenerate(&self) -> Result<String, String> {
+        let token = proxy::generate_access_token();
+        fs::write(paths::data_file(self.file)?, &token)
+            .map_err(|e| format!("保存访问令牌失败: {}", e))?;
+        if let Ok(mut cached) = self.cached.write() {
+            *cached = Some(token.clone());
+        }
+        Ok(token)
+    }
+    
+    /// 校验令牌
+    pub fn is_valid(&self, token: &str) -> bool {
+        self.get()
+            .map(|expected| proxy::tokens_match(&expected, token))
+            .unwrap_or(false)
+    }
+}
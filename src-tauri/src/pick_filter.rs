@@ -0,0 +1,117 @@
+//! 随机选取筛选模块
+//! 
+//! 随机选取时对搜索结果统一应用的筛选条件：时长范围、标题排除关键词和最低播放量，
+//! 持久化到应用数据目录，与电台自身的条件同时生效
+
+use crate::bilibili::SearchVideo;
+use crate::constants::{
+    PICK_FILTER_EXCLUDE_KEYWORDS, PICK_FILTER_MAX_DURATION_SECS, PICK_FILTER_MIN_DURATION_SECS,
+};
+use crate::paths;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::sync::RwLock;
+
+const CONFIG_FILE: &str = "pick_filter.json";
+
+/// 筛选条件
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PickFilter {
+    /// 时长范围（秒，不设置时不限制）
+    pub min_duration: Option<u64>,
+    pub max_duration: Option<u64>,
+    /// 标题包含这些关键词时排除（不区分大小写）
+    pub exclude_keywords: Vec<String>,
+    /// 最低播放量（播放量未知的视频不排除）
+    pub min_views: Option<u64>,
+}
+
+impl Default for PickFilter {
+    fn default() -> Self {
+        PickFilter {
+            min_duration: Some(PICK_FILTER_MIN_DURATION_SECS),
+            max_duration: Some(PICK_FILTER_MAX_DURATION_SECS),
+            exclude_keywords: PICK_FILTER_EXCLUDE_KEYWORDS
+                .iter()
+                .map(|keyword| keyword.to_string())
+                .collect(),
+            min_views: None,
+        }
+    }
+}
+
+impl PickFilter {
+    /// 搜索结果是否符合筛选条件
+    pub fn accepts(&self, video: &SearchVideo) -> bool {
+        if self.min_duration.map(|min| video.duration < min).unwrap_or(false)
+            || self.max_duration.map(|max| video.duration > max).unwrap_or(false)
+        {
+            return false;
+        }
+        if let (Some(min_views), Some(play)) = (self.min_views, video.play) {
+            if play < min_views {
+                return false;
+            }
+        }
+        let title = video.title.to_lowercase();
+        !self
+            .exclude_keywords
+            .iter()
+            .any(|keyword| title.contains(&keyword.to_lowercase()))
+    }
+}
+
+lazy_static! {
+    static ref PICK_FILTER: RwLock<Option<PickFilter>> = RwLock::new(None);
+}
+
+/// 获取筛选条件（首次调用时从数据目录读取）
+pub fn get() -> PickFilter {
+    if let Some(filter) = PICK_FILTER.read().ok().and_then(|f| f.clone()) {
+        return filter;
+    }
+    
+    let filter = paths::data_file(CONFIG_FILE)
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str::<PickFilter>(&content).ok())
+        .unwrap_or_default();
+    if let Ok(mut cached) = PICK_FILTER.write() {
+        *cached = Some(filter.clone());
+    }
+    filter
+}
+
+/// 更新筛选条件（返回整理后的条件）
+pub fn set(filter: PickFilter) -> Result<PickFilter, String> {
+    let min_duration = filter.min_duration.filter(|secs| *secs > 0);
+    let max_duration = filter.max_duration.filter(|secs| *secs > 0);
+    if let (Some(min), Some(max)) = (min_duration, max_duration) {
+        if min > max {
+            return Err("最短时长不能大于最长时长".to_string());
+        }
+    }
+    let mut exclude_keywords: Vec<String> = Vec::new();
+    for keyword in filter.exclude_keywords {
+        let keyword = keyword.trim().to_string();
+        if !keyword.is_empty() && !exclude_keywords.contains(&keyword) {
+            exclude_keywords.push(keyword);
+        }
+    }
+    let filter = PickFilter {
+        min_duration,
+        max_duration,
+        exclude_keywords,
+        min_views: filter.min_views.filter(|views| *views > 0),
+    };
+    
+    let content = serde_json::to_string_pretty(&filter).map_err(|e| e.to_string())?;
+    fs::write(paths::data_file(CONFIG_FILE)?, content)
+        .map_err(|e| format!("保存筛选条件失败: {}", e))?;
+    if let Ok(mut cached) = PICK_FILTER.write() {
+        *cached = Some(filter.clone());
+    }
+    Ok(filter)
+}
@@ -0,0 +1,276 @@
+//! 播放会话模块
+//! 
+//! 为 `/play` 路由解析同一音频的多个码率版本，根据实测吞吐量降低码率
+//! 
+//! 不同码率的文件长度不同，播放地址通过 `q` 参数固定码率，同一地址的所有区间请求始终使用同一版本，
+//! 保证 Content-Length/Content-Range 一致；降低码率只对之后生成的播放地址生效
+
+use crate::bilibili;
+use crate::constants::{
+    PLAY_SESSION_CAPACITY, PLAY_SESSION_TTL_SECS, QUALITY_DOWNGRADE_MARGIN,
+    THROUGHPUT_EWMA_ALPHA, THROUGHPUT_SAMPLE_BYTES,
+};
+use crate::events;
+use lazy_static::lazy_static;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// 同一音频的一个码率版本
+#[derive(Debug, Clone)]
+pub struct AudioStream {
+    pub url: String,
+    /// 码率（bit/s）
+    pub bandwidth: u64,
+}
+
+/// 降低码率建议事件
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct QualityDowngrade {
+    pub bvid: String,
+    pub cid: u64,
+    pub from_bandwidth: u64,
+    pub to_bandwidth: u64,
+    /// 实测吞吐量（bit/s）
+    pub throughput: u64,
+}
+
+/// 单个分 P 的播放会话
+struct PlaySession {
+    /// 按码率从高到低排列
+    streams: Vec<AudioStream>,
+    /// 创建会话时选择的版本（地址没有指定码率时使用）
+    initial: usize,
+    /// 之后生成的播放地址使用的版本
+    preferred: usize,
+    /// 吞吐量滑动平均（bit/s）
+    throughput: Option<f64>,
+    resolved_at: Instant,
+}
+
+type SessionKey = (String, u64);
+
+lazy_static! {
+    static ref PLAY_SESSIONS: Mutex<HashMap<SessionKey, PlaySession>> = Mutex::new(HashMap::new());
+}
+
+/// 解析视频分 P 的所有音频版本（按码率从高到低；老视频只有 durl 一个版本）
+pub async fn resolve_audio_streams(bvid: &str, cid: u64) -> Result<Vec<AudioStream>, String> {
+    let play_url = bilibili::get_play_url(bvid, cid).await?;
+    let mut streams: Vec<AudioStream> = play_url
+        .audio_streams()
+        .into_iter()
+        .map(|audio| AudioStream { url: audio.base_url, bandwidth: audio.bandwidth })
+        .collect();
+    
+    if streams.is_empty() {
+        if let Some(durl) = play_url.durl.as_ref().and_then(|list| list.first()) {
+            streams.push(AudioStream { url: durl.url.clone(), bandwidth: 0 });
+        }
+    }
+    if streams.is_empty() {
+        return Err("没有可用的音频地址".to_string());
+    }
+    Ok(streams)
+}
+
+/// 获取视频第一个分 P 的 cid
+pub async fn resolve_first_cid(bvid: &str) -> Result<u64, String> {
+    bilibili::get_pages(bvid)
+        .await?
+        .first()
+        .map(|page| page.cid)
+        .ok_or_else(|| "视频没有可播放的分 P".to_string())
+}
+
+/// 根据音质设置选择初始版本（与前端 high/medium/low 含义一致）
+fn initial_index(len: usize, quality: Option<&str>) -> usize {
+    match quality {
+        Some("medium") => len / 2,
+        Some("low") => len.saturating_sub(1),
+        _ => 0,
+    }
+}
+
+/// 码率不超过指定值的最高版本（都超过时使用最低码率）
+fn index_for(streams: &[AudioStream], bandwidth: u64) -> usize {
+    streams
+        .iter()
+        .position(|s| s.bandwidth <= bandwidth)
+        .unwrap_or(streams.len() - 1)
+}
+
+/// 创建或重置播放会话（返回播放地址应固定的码率）
+///
+/// 同一分 P 已因吞吐量不足降低过码率时，保留降低后的选择
+pub async fn open_session(bvid: &str, cid: u64, quality: Option<&str>) -> Result<u64, String> {
+    let streams = resolve_audio_streams(bvid, cid).await?;
+    let previous = PLAY_SESSIONS.lock().ok().and_then(|sessions| {
+        sessions
+            .get(&(bvid.to_string(), cid))
+            .map(|s| (s.preferred > s.initial, s.streams[s.preferred].bandwidth, s.throughput))
+    });
+    let mut index = initial_index(streams.len(), quality);
+    if let Some((true, bandwidth, _)) = previous {
+        index = index.max(index_for(&streams, bandwidth));
+    }
+    let bandwidth = streams[index].bandwidth;
+    insert_session(bvid, cid, PlaySession {
+        streams,
+        initial: index,
+        preferred: index,
+        throughput: previous.and_then(|(_, _, throughput)| throughput),
+        resolved_at: Instant::now(),
+    });
+    Ok(bandwidth)
+}
+
+fn insert_session(bvid: &str, cid: u64, session: PlaySession) {
+    if let Ok(mut sessions) = PLAY_SESSIONS.lock() {
+        sessions.insert((bvid.to_string(), cid), session);
+        // 超出容量时淘汰最早解析的会话
+        while sessions.len() > PLAY_SESSION_CAPACITY {
+            let oldest = sessions.iter()
+                .min_by_key(|(_, s)| s.resolved_at)
+                .map(|(k, _)| k.clone());
+            match oldest {
+                Some(key) => sessions.remove(&key),
+                None => break,
+            };
+        }
+    }
+}
+
+/// 获取本次请求应使用的音频地址
+///
+/// 指定码率时使用不超过该码率的最高版本，否则使用创建会话时选择的版本；
+/// 会话不存在或地址即将过期时重新解析
+pub async fn stream_url_for(bvid: &str, cid: u64, bandwidth: Option<u64>) -> Result<String, String> {
+    let key = (bvid.to_string(), cid);
+    let ttl = Duration::from_secs(PLAY_SESSION_TTL_SECS);
+    
+    let fresh = PLAY_SESSIONS.lock()
+        .map(|sessions| matches!(sessions.get(&key), Some(s) if s.resolved_at.elapsed() <= ttl))
+        .unwrap_or(false);
+    if !fresh {
+        // 重新解析时保留之前的码率选择
+        let previous = PLAY_SESSIONS.lock().ok().and_then(|sessions| {
+            sessions.get(&key).map(|s| {
+                (s.streams[s.initial].bandwidth, s.streams[s.preferred].bandwidth, s.throughput)
+            })
+        });
+        let streams = resolve_audio_streams(bvid, cid).await?;
+        let (initial, preferred) = match previous {
+            Some((initial, preferred, _)) => (index_for(&streams, initial), index_for(&streams, preferred)),
+            None => (0, 0),
+        };
+        insert_session(bvid, cid, PlaySession {
+            streams,
+            initial,
+            preferred,
+            throughput: previous.and_then(|(_, _, throughput)| throughput),
+            resolved_at: Instant::now(),
+        });
+    }
+    
+    let sessions = PLAY_SESSIONS.lock().map_err(|_| "播放会话异常".to_string())?;
+    let session = sessions.get(&key).ok_or_else(|| "播放会话不存在".to_string())?;
+    let index = match bandwidth {
+        Some(bandwidth) => index_for(&session.streams, bandwidth),
+        None => session.initial,
+    };
+    Ok(session.streams[index].url.clone())
+}
+
+/// 之后生成的播放地址应固定的码率（会话不存在时返回 None）
+pub fn preferred_bandwidth(bvid: &str, cid: u64) -> Option<u64> {
+    let sessions = PLAY_SESSIONS.lock().ok()?;
+    let session = sessions.get(&(bvid.to_string(), cid))?;
+    Some(session.streams[session.preferred].bandwidth)
+}
+
+/// 记录一次吞吐量采样，低于当前码率时降低之后生成的播放地址的码率并通知前端
+/// （前端重新获取播放地址后生效）
+pub fn record_throughput(bvid: &str, cid: u64, bits_per_sec: f64) {
+    let downgrade = {
+        let Ok(mut sessions) = PLAY_SESSIONS.lock() else {
+            return;
+        };
+        let Some(session) = sessions.get_mut(&(bvid.to_string(), cid)) else {
+            return;
+        };
+        let throughput = match session.throughput {
+            Some(prev) => prev + THROUGHPUT_EWMA_ALPHA * (bits_per_sec - prev),
+            None => bits_per_sec,
+        };
+        session.throughput = Some(throughput);
+        
+        let current = &session.streams[session.preferred];
+        let needed = current.bandwidth as f64 * QUALITY_DOWNGRADE_MARGIN;
+        if session.preferred + 1 >= session.streams.len() || throughput >= needed {
+            return;
+        }
+        
+        // 选择吞吐量能承受的最高码率，没有则选最低码率
+        let from_bandwidth = current.bandwidth;
+        let target = session.streams.iter()
+            .position(|s| (s.bandwidth as f64 * QUALITY_DOWNGRADE_MARGIN) <= throughput)
+            .unwrap_or(session.streams.len() - 1)
+            .max(session.preferred + 1);
+        session.preferred = target;
+        QualityDowngrade {
+            bvid: bvid.to_string(),
+            cid,
+            from_bandwidth,
+            to_bandwidth: session.streams[target].bandwidth,
+            throughput: throughput as u64,
+        }
+    };
+    
+    events::emit("audio-quality-downgrade", downgrade);
+}
+
+/// 吞吐量探针：统计上游传输开头一段数据的速度
+///
+/// 只累计等待上游数据块的时间，缓冲区已满、等待客户端读取的时间不计入
+pub struct ThroughputProbe {
+    bvid: String,
+    cid: u64,
+    first_chunk_seen: bool,
+    bytes: u64,
+    waited: Duration,
+    done: bool,
+}
+
+impl ThroughputProbe {
+    pub fn new(bvid: &str, cid: u64) -> Self {
+        Self {
+            bvid: bvid.to_string(),
+            cid,
+            first_chunk_seen: false,
+            bytes: 0,
+            waited: Duration::ZERO,
+            done: false,
+        }
+    }
+    
+    /// 收到一个上游数据块，waited 为等待该块的时间（第一个块包含首字节延迟，不计入）
+    pub fn on_chunk(&mut self, len: usize, waited: Duration) {
+        if self.done {
+            return;
+        }
+        if !self.first_chunk_seen {
+            self.first_chunk_seen = true;
+            return;
+        }
+        self.bytes += len as u64;
+        self.waited += waited;
+        if self.bytes >= THROUGHPUT_SAMPLE_BYTES {
+            self.done = true;
+            let secs = self.waited.as_secs_f64().max(0.001);
+            record_throughput(&self.bvid, self.cid, self.bytes as f64 * 8.0 / secs);
+        }
+    }
+}
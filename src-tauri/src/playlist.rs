@@ -0,0 +1,119 @@
+//! 播放列表模块
+//! 
+//! 生成 M3U8 播放列表，供 mpv、VLC 等外部播放器通过代理收听播放队列或收藏
+//! 
+//! 列表中的地址使用持久化的播放列表令牌（与局域网令牌分开），写入磁盘的播放列表在应用重启后仍可使用
+
+use crate::persisted_token::PersistedToken;
+use crate::play_session;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+/// 播放列表中的曲目（与前端 VideoItem 字段一致）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaylistTrack {
+    pub bvid: String,
+    /// 分 P 的 cid（缺省时使用第一个分 P）
+    pub cid: Option<u64>,
+    pub title: String,
+    /// 时长（秒）
+    pub duration: Option<u64>,
+}
+
+lazy_static! {
+    /// `/playlist.m3u8` 提供的曲目
+    static ref CURRENT_PLAYLIST: RwLock<Vec<PlaylistTrack>> = RwLock::new(Vec::new());
+    /// 播放列表访问令牌（代理除本次运行的令牌外也接受它）
+    static ref PLAYLIST_TOKEN: PersistedToken = PersistedToken::new("playlist_token");
+}
+
+/// 获取播放列表访问令牌
+pub fn token() -> Result<String, String> {
+    PLAYLIST_TOKEN.get()
+}
+
+/// 校验播放列表访问令牌
+pub fn is_valid_token(token: &str) -> bool {
+    PLAYLIST_TOKEN.is_valid(token)
+}
+
+/// 重新生成播放列表访问令牌（已导出的播放列表全部失效）
+pub fn regenerate_token() -> Result<(), String> {
+    PLAYLIST_TOKEN.regenerate().map(|_| ())
+}
+
+/// 补全缺少的 cid（解析失败的曲目会被跳过）
+async fn resolve_tracks(tracks: Vec<PlaylistTrack>) -> Vec<PlaylistTrack> {
+    let mut resolved = Vec::with_capacity(tracks.len());
+    for mut track in tracks {
+        if track.cid.is_none() {
+            match play_session::resolve_first_cid(&track.bvid).await {
+                Ok(cid) => track.cid = Some(cid),
+                Err(e) => {
+                    eprintln!("[Playlist] 跳过 {}: {}", track.bvid, e);
+                    continue;
+                }
+            }
+        }
+        resolved.push(track);
+    }
+    resolved
+}
+
+/// 设置 `/playlist.m3u8` 提供的曲目
+pub async fn set_playlist(tracks: Vec<PlaylistTrack>) {
+    let tracks = resolve_tracks(tracks).await;
+    if let Ok(mut playlist) = CURRENT_PLAYLIST.write() {
+        *playlist = tracks;
+    }
+}
+
+/// 当前提供的曲目
+pub fn current_playlist() -> Vec<PlaylistTrack> {
+    CURRENT_PLAYLIST.read().map(|p| p.clone()).unwrap_or_default()
+}
+
+/// 生成 M3U8 内容（地址指向代理的 `/play` 路由，base_url 如 `http://127.0.0.1:47810`）
+pub fn render(base_url: &str, tracks: &[PlaylistTrack]) -> Result<String, String> {
+    let token = token()?;
+    let mut content = String::from("#EXTM3U\n");
+    for track in tracks {
+        let Some(cid) = track.cid else {
+            continue;
+        };
+        // 标题中的换行会破坏列表格式
+        let title: String = track.title
+            .chars()
+            .map(|c| if c == '\r' || c == '\n' { ' ' } else { c })
+            .collect();
+        let duration = track.duration.map(|d| d as i64).unwrap_or(-1);
+        content.push_str(&format!(
+            "#EXTINF:{},{}\n{}/play/{}/{}/{}\n",
+            duration,
+            title.trim(),
+            base_url,
+            token,
+            urlencoding::encode(&track.bvid),
+            cid
+        ));
+    }
+    Ok(content)
+}
+
+/// 把播放列表写入文件（返回写入的路径）
+pub async fn export(base_url: &str, tracks: Vec<PlaylistTrack>, path: String) -> Result<String, String> {
+    let tracks = resolve_tracks(tracks).await;
+    let mut path = PathBuf::from(path);
+    if path.extension().is_none() {
+        path.set_extension("m3u8");
+    }
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    fs::write(&path, render(base_url, &tracks)?).map_err(|e| format!("写入播放列表失败: {}", e))?;
+    Ok(path.to_string_lossy().to_string())
+}
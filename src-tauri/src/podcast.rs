@@ -0,0 +1,237 @@
+//! 播客订阅模块
+//! 
+//! 在局域网播放服务上提供 RSS 2.0 播客订阅（含 iTunes 标签），
+//! 媒体库中的每个收藏和下载文件都是一集，音频地址指回局域网播放服务
+//! 
+//! 订阅内容按媒体库变更序号和下载目录的文件列表缓存，二者变化时重新生成
+
+use crate::constants::DLNA_FRIENDLY_NAME;
+use crate::lan::{self, escape_html};
+use crate::library;
+use axum::{
+    extract::Query,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use lazy_static::lazy_static;
+use serde::Deserialize;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::net::SocketAddr;
+use std::sync::RwLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 订阅请求参数
+#[derive(Deserialize)]
+struct FeedQuery {
+    token: Option<String>,
+}
+
+/// 已生成的订阅内容
+struct CachedFeed {
+    key: u64,
+    xml: String,
+}
+
+lazy_static! {
+    static ref FEED_CACHE: RwLock<Option<CachedFeed>> = RwLock::new(None);
+}
+
+/// 播客路由（挂载在局域网播放服务上）
+pub fn routes() -> Router {
+    Router::new().route("/podcast.xml", get(handle_feed))
+}
+
+/// 订阅地址（需要先开启局域网播放）
+pub fn feed_url(addr: SocketAddr, token: &str) -> String {
+    format!("http://{}/podcast.xml?token={}", addr, token)
+}
+
+async fn handle_feed(Query(query): Query<FeedQuery>) -> Response {
+    if !query.token.as_deref().is_some_and(lan::is_valid_token) {
+        return (StatusCode::FORBIDDEN, "访问令牌无效").into_response();
+    }
+    let (Some(addr), Ok(token)) = (lan::server_addr().await, lan::lan_token()) else {
+        return (StatusCode::SERVICE_UNAVAILABLE, "局域网播放未开启").into_response();
+    };
+    
+    (
+        [(header::CONTENT_TYPE, "application/rss+xml; charset=utf-8")],
+        feed(addr, &token),
+    )
+        .into_response()
+}
+
+/// 获取订阅内容（媒体库、下载目录和访问令牌都没有变化时使用缓存）
+fn feed(addr: SocketAddr, token: &str) -> String {
+    let favorites = library::favorites();
+    let downloads = library::download_files();
+    
+    let mut hasher = DefaultHasher::new();
+    library::revision().hash(&mut hasher);
+    downloads.hash(&mut hasher);
+    addr.hash(&mut hasher);
+    // 订阅中的地址都带有令牌，重新生成令牌后不能再返回旧内容
+    token.hash(&mut hasher);
+    let key = hasher.finish();
+    
+    if let Some(cached) = FEED_CACHE.read().ok().as_ref().and_then(|c| c.as_ref()) {
+        if cached.key == key {
+            return cached.xml.clone();
+        }
+    }
+    
+    let xml = render(addr, token, &favorites, &downloads);
+    if let Ok(mut cache) = FEED_CACHE.write() {
+        *cache = Some(CachedFeed { key, xml: xml.clone() });
+    }
+    xml
+}
+
+/// B 站封面地址可能省略协议或使用 http，统一为 https
+fn normalize_pic(pic: &str) -> String {
+    if let Some(rest) = pic.strip_prefix("//") {
+        format!("https://{}", rest)
+    } else if let Some(rest) = pic.strip_prefix("http://") {
+        format!("https://{}", rest)
+    } else {
+        pic.to_string()
+    }
+}
+
+/// 生成一集
+fn episode(
+    title: &str,
+    guid: &str,
+    pub_date: u64,
+    url: &str,
+    length: u64,
+    mime: &str,
+    extra: &str,
+) -> String {
+    format!(
+        "<item><title>{}</title><guid isPermaLink=\"false\">{}</guid><pubDate>{}</pubDate>\
+         <enclosure url=\"{}\" length=\"{}\" type=\"{}\"/>{}</item>",
+        escape_html(title),
+        escape_html(guid),
+        rfc2822(pub_date),
+        escape_html(url),
+        length,
+        mime,
+        extra
+    )
+}
+
+/// 生成 RSS 文档
+fn render(
+    addr: SocketAddr,
+    token: &str,
+    favorites: &[lan::LanTrack],
+    downloads: &[library::DownloadFile],
+) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let mut items = String::new();
+    
+    for track in favorites {
+        let mut extra = String::new();
+        if let Some(duration) = track.duration {
+            extra.push_str(&format!("<itunes:duration>{}</itunes:duration>", duration));
+        }
+        if let Some(author) = &track.author {
+            extra.push_str(&format!("<itunes:author>{}</itunes:author>", escape_html(author)));
+        }
+        if let Some(pic) = &track.pic {
+            extra.push_str(&format!("<itunes:image href=\"{}\"/>", escape_html(&normalize_pic(pic))));
+        }
+        items.push_str(&episode(
+            &track.title,
+            &format!("favorite-{}-{}", track.bvid, track.cid),
+            track.added_at.map(|ms| ms / 1000).unwrap_or(now),
+            &format!("http://{}/track/{}/{}?token={}", addr, track.bvid, track.cid, token),
+            0,
+            "audio/mp4",
+            &extra,
+        ));
+    }
+    
+    for file in downloads {
+        let modified = file.modified
+            .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs())
+            .unwrap_or(now);
+        let title = file.name.rsplit_once('.').map(|(stem, _)| stem).unwrap_or(&file.name);
+        items.push_str(&episode(
+            title,
+            &format!("download-{}", file.name),
+            modified,
+            &format!("http://{}/download/{}?token={}", addr, urlencoding::encode(&file.name), token),
+            file.size,
+            library::media_mime(&file.name),
+            "",
+        ));
+    }
+    
+    let image = favorites
+        .iter()
+        .find_map(|track| track.pic.as_deref())
+        .map(|pic| format!("<itunes:image href=\"{}\"/>", escape_html(&normalize_pic(pic))))
+        .unwrap_or_default();
+    
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+         <rss version=\"2.0\" xmlns:itunes=\"http://www.itunes.com/dtds/podcast-1.0.dtd\" \
+         xmlns:atom=\"http://www.w3.org/2005/Atom\"><channel>\
+         <title>{name}</title><link>http://{addr}/?token={token}</link>\
+         <atom:link href=\"{feed}\" rel=\"self\" type=\"application/rss+xml\"/>\
+         <description>收藏和下载的相声</description><language>zh-cn</language>\
+         <lastBuildDate>{date}</lastBuildDate>\
+         <itunes:author>{name}</itunes:author><itunes:summary>收藏和下载的相声</itunes:summary>\
+         <itunes:category text=\"Comedy\"/><itunes:explicit>false</itunes:explicit>{image}\
+         {items}</channel></rss>",
+        name = DLNA_FRIENDLY_NAME,
+        addr = addr,
+        token = token,
+        feed = escape_html(&feed_url(addr, token)),
+        date = rfc2822(now),
+        image = image,
+        items = items,
+    )
+}
+
+/// 格式化为 RFC 2822 时间（UTC）
+fn rfc2822(secs: u64) -> String {
+    const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    
+    let days = (secs / 86400) as i64;
+    let time = secs % 86400;
+    
+    // 由天数换算公历日期（Howard Hinnant 的 civil_from_days 算法）
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} +0000",
+        WEEKDAYS[(days % 7) as usize],
+        day,
+        MONTHS[(month - 1) as usize],
+        year,
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
@@ -2,113 +2,977 @@
 //! 
 //! 提供 HTTP 代理服务器功能，用于绕过 CORS 限制和实现流式播放
 
-use crate::constants::{PROXY_PORT_RANGE_END, PROXY_PORT_RANGE_START};
-use crate::http_client::{add_bilibili_headers, get_http_client};
+use crate::constants::{
+    AUDIO_PREFETCH_BYTES, AUDIO_PREFETCH_DELAY_MS, IMAGE_CACHE_MAX_AGE_SECS,
+    IMAGE_MAX_BYTES, PROXY_ALLOWED_METHODS, PROXY_MAX_REDIRECTS,
+    PROXY_CORS_MAX_AGE_SECS, PROXY_DEFAULT_ALLOWED_HOSTS, PROXY_DEFAULT_PORT,
+    PROXY_SHUTDOWN_TIMEOUT_SECS, PROXY_TOKEN_LENGTH,
+};
+use crate::http_client::{add_bilibili_headers, build_client};
+use crate::access_log;
+use crate::audio_cache;
+use crate::image_cache::{self, ImageSize};
+use crate::inflight;
+use crate::metrics::{MetricsSnapshot, PROXY_METRICS};
+use crate::play_session::{self, ThroughputProbe};
+use crate::playlist::{self, PlaylistTrack};
+use crate::proxy_config::{self, ProxyConfig};
 use axum::{
-    body::Body,
-    extract::{Path, Request},
-    http::{header, HeaderName, HeaderValue, StatusCode},
-    response::Response,
+    body::{Body, Bytes},
+    extract::{Path, Query},
+    http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode},
+    middleware,
+    response::{IntoResponse, Response},
     routing::get,
-    Router,
+    Json, Router,
 };
-use futures::StreamExt;
+use futures::{Stream, StreamExt};
 use lazy_static::lazy_static;
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::{oneshot, Mutex};
+use tokio::task::JoinHandle;
 use tower::ServiceBuilder;
-use tower_http::cors::CorsLayer;
+use tower_http::cors::{Any, CorsLayer};
+
+/// 运行中的代理服务器句柄
+struct ProxyServerHandle {
+    /// 启动序号（用于区分重启前后的服务任务）
+    generation: u64,
+    addr: SocketAddr,
+    started_at: Instant,
+    shutdown_tx: oneshot::Sender<()>,
+    task: JoinHandle<()>,
+}
+
+/// 代理服务器状态
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ProxyStatus {
+    pub running: bool,
+    pub port: Option<u16>,
+    /// 代理地址（如 `http://127.0.0.1:47810`）
+    pub base_url: Option<String>,
+    pub uptime_secs: u64,
+}
+
+// 代理服务器句柄与访问令牌（令牌每次启动随机生成，防止被其他本地进程当作开放中继）
+lazy_static! {
+    static ref PROXY_SERVER: Arc<Mutex<Option<ProxyServerHandle>>> = Arc::new(Mutex::new(None));
+    static ref PROXY_ACCESS_TOKEN: String = generate_access_token();
+}
+
+// 当前的预取任务（新的预取请求会取消旧任务）
+lazy_static! {
+    static ref PREFETCH_TASK: Mutex<Option<JoinHandle<()>>> = Mutex::new(None);
+}
+
+static PROXY_GENERATION: AtomicU64 = AtomicU64::new(0);
 
-// 代理服务器端口
+// 允许代理的上游主机后缀（可通过命令修改，防止 SSRF）
 lazy_static! {
-    static ref PROXY_SERVER_PORT: Arc<Mutex<Option<u16>>> = Arc::new(Mutex::new(None));
+    static ref ALLOWED_UPSTREAM_HOSTS: RwLock<Vec<String>> = RwLock::new(
+        PROXY_DEFAULT_ALLOWED_HOSTS.iter().map(|h| h.to_string()).collect()
+    );
 }
 
-async fn bind_available_port() -> Result<(u16, tokio::net::TcpListener), String> {
-    for port in PROXY_PORT_RANGE_START..PROXY_PORT_RANGE_END {
-        match tokio::net::TcpListener::bind(format!("127.0.0.1:{}", port)).await {
-            Ok(listener) => return Ok((port, listener)),
-            Err(_) => continue,
+// 代理访问上游使用的客户端（重定向的每一跳都重新校验目标地址，防止经由白名单主机跳转到任意地址）
+lazy_static! {
+    static ref UPSTREAM_CLIENT: Result<reqwest::Client, String> =
+        build_client(reqwest::redirect::Policy::custom(|attempt| {
+            if attempt.previous().len() >= PROXY_MAX_REDIRECTS {
+                return attempt.error("重定向次数过多");
+            }
+            match check_upstream_url(attempt.url().as_str()) {
+                Ok(()) => attempt.follow(),
+                Err(reason) => attempt.error(reason),
+            }
+        }));
+}
+
+/// 生成随机访问令牌
+pub(crate) fn generate_access_token() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(PROXY_TOKEN_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+/// 校验本次运行的访问令牌
+fn is_valid_token(token: &str) -> bool {
+    tokens_match(PROXY_ACCESS_TOKEN.as_str(), token)
+}
+
+/// 校验播放地址的访问令牌（也接受持久化的播放列表令牌，供写入磁盘的播放列表使用）
+///
+/// 播放列表令牌只能用于 `/play` 和 `/playlist.m3u8`，不能用于转发任意上游地址的路由
+fn is_valid_play_token(token: &str) -> bool {
+    is_valid_token(token) || playlist::is_valid_token(token)
+}
+
+/// 比较令牌（逐字节比较，避免提前返回泄露时序信息）
+pub(crate) fn tokens_match(expected: &str, actual: &str) -> bool {
+    let expected = expected.as_bytes();
+    let actual = actual.as_bytes();
+    if expected.len() != actual.len() {
+        return false;
+    }
+    expected
+        .iter()
+        .zip(actual)
+        .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+        == 0
+}
+
+/// 获取当前允许的上游主机后缀
+pub fn get_allowed_hosts() -> Vec<String> {
+    ALLOWED_UPSTREAM_HOSTS
+        .read()
+        .map(|hosts| hosts.clone())
+        .unwrap_or_default()
+}
+
+/// 设置允许的上游主机后缀（为空时恢复默认列表）
+pub fn set_allowed_hosts(hosts: Vec<String>) -> Result<Vec<String>, String> {
+    let mut normalized = Vec::new();
+    for host in hosts {
+        let host = host.trim().trim_start_matches('.').to_lowercase();
+        if host.is_empty() {
+            continue;
+        }
+        if !host.chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-') {
+            return Err(format!("无效的主机后缀: {}", host));
         }
+        if !normalized.contains(&host) {
+            normalized.push(host);
+        }
+    }
+    if normalized.is_empty() {
+        normalized = PROXY_DEFAULT_ALLOWED_HOSTS.iter().map(|h| h.to_string()).collect();
     }
-    Err("无法找到可用端口".into())
+    
+    let mut guard = ALLOWED_UPSTREAM_HOSTS
+        .write()
+        .map_err(|_| "更新主机白名单失败".to_string())?;
+    *guard = normalized.clone();
+    Ok(normalized)
+}
+
+/// 校验上游 URL：仅允许 http/https 协议且主机在白名单内（返回拒绝原因）
+fn check_upstream_url(url: &str) -> Result<(), String> {
+    let parsed = reqwest::Url::parse(url).map_err(|_| format!("无法解析上游地址: {}", url))?;
+    
+    if parsed.scheme() != "http" && parsed.scheme() != "https" {
+        return Err(format!("不允许的协议: {}", parsed.scheme()));
+    }
+    if !parsed.username().is_empty() || parsed.password().is_some() {
+        return Err("上游地址不允许包含认证信息".to_string());
+    }
+    
+    // domain() 对 IP 地址返回 None，因此同时拒绝了直接访问内网 IP
+    let host = match parsed.domain() {
+        Some(domain) => domain.trim_end_matches('.').to_lowercase(),
+        None if parsed.host().is_some() => return Err("不允许直接访问 IP 地址".to_string()),
+        None => return Err("上游地址缺少主机名".to_string()),
+    };
+    
+    let allowed = get_allowed_hosts().iter().any(|suffix| {
+        host == *suffix || host.ends_with(&format!(".{}", suffix))
+    });
+    if !allowed {
+        return Err(format!("上游主机不在白名单内: {}", host));
+    }
+    Ok(())
+}
+
+/// 按配置绑定监听地址
+///
+/// 依次尝试优先端口（未设置时沿用上次的端口）和系统分配的端口；
+/// 开启 IPv6 时先尝试 `[::1]`，不可用时回退到 127.0.0.1
+async fn bind_listener() -> Result<(SocketAddr, tokio::net::TcpListener), String> {
+    let config = proxy_config::get();
+    let mut hosts = Vec::new();
+    if config.ipv6 {
+        hosts.push(IpAddr::V6(Ipv6Addr::LOCALHOST));
+    }
+    hosts.push(IpAddr::V4(Ipv4Addr::LOCALHOST));
+    
+    let preferred = config.preferred_port
+        .or(config.last_address.map(|addr| addr.port()))
+        .unwrap_or(PROXY_DEFAULT_PORT);
+    let mut ports = vec![preferred];
+    if !config.fixed_port {
+        ports.push(0);
+    }
+    
+    let mut last_error = String::new();
+    for host in hosts {
+        for port in &ports {
+            match tokio::net::TcpListener::bind(SocketAddr::new(host, *port)).await {
+                Ok(listener) => {
+                    let addr = listener.local_addr().map_err(|e| e.to_string())?;
+                    return Ok((addr, listener));
+                }
+                Err(e) => last_error = format!("{}: {}", SocketAddr::new(host, *port), e),
+            }
+        }
+    }
+    Err(format!("无法绑定代理端口 ({})", last_error))
 }
 
 /// 启动代理服务器（返回代理端口）
 pub async fn start_proxy_server() -> Result<u16, String> {
-    let mut port_guard = PROXY_SERVER_PORT.lock().await;
-    if let Some(port) = *port_guard {
-        return Ok(port);
+    let mut server_guard = PROXY_SERVER.lock().await;
+    if let Some(server) = server_guard.as_ref() {
+        // 服务任务已退出时端口不再可用，需要重新启动
+        if !server.task.is_finished() {
+            return Ok(server.addr.port());
+        }
+        *server_guard = None;
     }
-
+    
     // 直接绑定端口，避免“先探测后绑定”期间的抢占
-    let (port, listener) = bind_available_port().await?;
-    let port_clone = port;
-
-    tokio::spawn(async move {
+    let (addr, listener) = bind_listener().await?;
+    proxy_config::record_address(addr);
+    let generation = PROXY_GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    
+    let task = tokio::spawn(async move {
         let app = Router::new()
-            .route("/proxy/:encoded_url", get(handle_proxy_request))
-            .layer(ServiceBuilder::new().layer(CorsLayer::permissive()));
-
-        if let Err(err) = axum::serve(listener, app).await {
-            eprintln!("[Proxy] 启动失败: {:?}", err);
+            .route(
+                "/proxy/:token/:encoded_url",
+                get(handle_proxy_request).head(handle_proxy_head),
+            )
+            .route(
+                "/play/:token/:bvid/:cid",
+                get(handle_play_request).head(handle_play_head),
+            )
+            .route("/playlist.m3u8", get(handle_playlist))
+            .route("/image/:encoded_url", get(handle_image_request))
+            .route("/health", get(handle_health))
+            .route("/metrics", get(handle_metrics))
+            .layer(ServiceBuilder::new().layer(cors_layer()))
+            .layer(middleware::from_fn(access_log::middleware));
+        
+        let result = axum::serve(listener, app)
+            .with_graceful_shutdown(async {
+                let _ = shutdown_rx.await;
+            })
+            .await;
+        if let Err(err) = result {
+            eprintln!("[Proxy] 服务异常退出: {:?}", err);
+        }
+        
+        // 服务退出后清除记录的端口，下次调用会重新启动
+        let mut server_guard = PROXY_SERVER.lock().await;
+        if server_guard.as_ref().map(|s| s.generation) == Some(generation) {
+            *server_guard = None;
         }
     });
+    
+    *server_guard = Some(ProxyServerHandle {
+        generation,
+        addr,
+        started_at: Instant::now(),
+        shutdown_tx,
+        task,
+    });
+    Ok(addr.port())
+}
+
+/// 启动代理服务器并返回基础地址（IPv6 地址带方括号）
+async fn proxy_base_url() -> Result<String, String> {
+    start_proxy_server().await?;
+    proxy_status()
+        .await
+        .base_url
+        .ok_or_else(|| "代理服务器未运行".to_string())
+}
+
+/// 停止代理服务器（等待现有连接结束，超时后强制终止）
+pub async fn stop_proxy_server() -> Result<(), String> {
+    let server = PROXY_SERVER.lock().await.take();
+    let Some(server) = server else {
+        return Ok(());
+    };
+    
+    let _ = server.shutdown_tx.send(());
+    let mut task = server.task;
+    let timeout = Duration::from_secs(PROXY_SHUTDOWN_TIMEOUT_SECS);
+    if tokio::time::timeout(timeout, &mut task).await.is_err() {
+        // 正在播放的流式连接可能一直不结束，超时后直接终止
+        task.abort();
+    }
+    Ok(())
+}
 
-    *port_guard = Some(port_clone);
-    Ok(port_clone)
+/// 重启代理服务器（返回新的代理端口）
+pub async fn restart_proxy_server() -> Result<u16, String> {
+    stop_proxy_server().await?;
+    start_proxy_server().await
+}
+
+/// 获取代理服务器状态
+pub async fn proxy_status() -> ProxyStatus {
+    let server_guard = PROXY_SERVER.lock().await;
+    match server_guard.as_ref() {
+        Some(server) if !server.task.is_finished() => ProxyStatus {
+            running: true,
+            port: Some(server.addr.port()),
+            base_url: Some(format!("http://{}", server.addr)),
+            uptime_secs: server.started_at.elapsed().as_secs(),
+        },
+        _ => ProxyStatus {
+            running: false,
+            port: None,
+            base_url: None,
+            uptime_secs: 0,
+        },
+    }
 }
 
 /// 处理代理请求（支持 Range 请求，实现流式播放）
 async fn handle_proxy_request(
+    Path((token, encoded_url)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Result<Response<Body>, (StatusCode, String)> {
+    let url = validate_request(&token, &encoded_url)?;
+    let range = headers
+        .get("range")
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string());
+    serve_audio(url, range, None).await
+}
+
+/// 转发音频请求（预取缓存 → 合并的上游传输 → 直接转发）
+///
+/// probe 用于统计上游传输速度，只在发起新的合并传输时采样，命中预取缓存或直接转发时不统计
+async fn serve_audio(
+    url: String,
+    range: Option<String>,
+    probe: Option<ThroughputProbe>,
+) -> Result<Response<Body>, (StatusCode, String)> {
+    // 优先使用预取缓存中的开头数据
+    if let Some(prefix) = audio_cache::get(&url) {
+        if let Some(response) = serve_from_prefix(&url, prefix, range.as_deref()).await {
+            PROXY_METRICS.record_audio_cache_hit();
+            access_log::record_cache_hit();
+            return Ok(response);
+        }
+    }
+    PROXY_METRICS.record_audio_cache_miss();
+    
+    // 单区间请求与其他并发的重叠请求共享上游传输
+    let parsed_range = match range.as_deref() {
+        Some(range) => parse_range(range),
+        None => Some((0, None)),
+    };
+    if let Some((start, end)) = parsed_range {
+        let fetch_url = url.clone();
+        // 上游传输在后台任务中进行，需要带上当前请求的日志上下文
+        let trace = access_log::current();
+        let (meta, last_byte, stream) = inflight::subscribe(
+            audio_cache::cache_key(&url),
+            start,
+            end,
+            probe,
+            move |upstream_range| access_log::with_trace(trace, async move {
+                fetch_upstream(&fetch_url, Some(&upstream_range), reqwest::Method::GET).await
+            }),
+        )
+        .await?;
+        let response_builder = range_response_builder(
+            &meta.content_type,
+            start,
+            last_byte,
+            meta.total,
+            range.is_some(),
+        );
+        return Ok(response_builder.body(metered_body(stream)).unwrap());
+    }
+    
+    // 多区间等无法合并的请求直接转发
+    let response = fetch_upstream(&url, range.as_deref(), reqwest::Method::GET).await?;
+    let response_builder = build_response_headers(&response);
+    
+    // 将响应体转换为流（这会移动 response）
+    let stream = response.bytes_stream()
+        .map(|result| result.map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err)));
+    
+    Ok(response_builder.body(metered_body(stream)).unwrap())
+}
+
+/// 处理 HEAD 请求（只返回上游的长度和类型，不传输响应体）
+async fn handle_proxy_head(
+    Path((token, encoded_url)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Result<Response<Body>, (StatusCode, String)> {
+    let url = validate_request(&token, &encoded_url)?;
+    let range = headers
+        .get("range")
+        .and_then(|v| v.to_str().ok());
+    
+    let response = fetch_upstream(&url, range, reqwest::Method::HEAD).await?;
+    let response_builder = build_response_headers(&response);
+    
+    // response 在此处被丢弃，GET 回退时也不会继续读取响应体
+    Ok(response_builder.body(Body::empty()).unwrap())
+}
+
+/// 处理播放请求（按 bvid 和 cid 选择音频版本，吞吐量不足时后续播放降低码率）
+async fn handle_play_request(
+    Path((token, bvid, cid)): Path<(String, String, u64)>,
+    Query(query): Query<PlayQuery>,
+    headers: HeaderMap,
+) -> Result<Response<Body>, (StatusCode, String)> {
+    if !is_valid_play_token(&token) {
+        return Err((StatusCode::FORBIDDEN, "访问令牌无效".to_string()));
+    }
+    let range = headers
+        .get("range")
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string());
+    serve_track(&bvid, cid, query.q, range).await
+}
+
+/// 处理播放地址的 HEAD 请求
+async fn handle_play_head(
+    Path((token, bvid, cid)): Path<(String, String, u64)>,
+    Query(query): Query<PlayQuery>,
+    headers: HeaderMap,
+) -> Result<Response<Body>, (StatusCode, String)> {
+    if !is_valid_play_token(&token) {
+        return Err((StatusCode::FORBIDDEN, "访问令牌无效".to_string()));
+    }
+    let range = headers
+        .get("range")
+        .and_then(|v| v.to_str().ok());
+    serve_track_head(&bvid, cid, query.q, range).await
+}
+
+/// 按 bvid 和 cid 转发音频（供本机播放和局域网播放共用，调用方负责校验令牌）
+///
+/// bandwidth 为播放地址固定的码率，未指定时使用创建会话时选择的版本
+pub(crate) async fn serve_track(
+    bvid: &str,
+    cid: u64,
+    bandwidth: Option<u64>,
+    range: Option<String>,
+) -> Result<Response<Body>, (StatusCode, String)> {
+    PROXY_METRICS.record_request();
+    let url = track_upstream_url(bvid, cid, bandwidth).await?;
+    serve_audio(url, range, Some(ThroughputProbe::new(bvid, cid))).await
+}
+
+/// 按 bvid 和 cid 返回音频的长度和类型
+pub(crate) async fn serve_track_head(
+    bvid: &str,
+    cid: u64,
+    bandwidth: Option<u64>,
+    range: Option<&str>,
+) -> Result<Response<Body>, (StatusCode, String)> {
+    PROXY_METRICS.record_request();
+    let url = track_upstream_url(bvid, cid, bandwidth).await?;
+    
+    let response = fetch_upstream(&url, range, reqwest::Method::HEAD).await?;
+    Ok(build_response_headers(&response).body(Body::empty()).unwrap())
+}
+
+/// 获取本次播放请求对应的上游地址
+async fn track_upstream_url(
+    bvid: &str,
+    cid: u64,
+    bandwidth: Option<u64>,
+) -> Result<String, (StatusCode, String)> {
+    let url = play_session::stream_url_for(bvid, cid, bandwidth)
+        .await
+        .map_err(|e| {
+            access_log::record_error(e.clone());
+            (StatusCode::BAD_GATEWAY, e)
+        })?;
+    if let Err(reason) = check_upstream_url(&url) {
+        return Err((StatusCode::FORBIDDEN, reason));
+    }
+    Ok(url)
+}
+
+/// 包装响应流：统计发送字节数，守卫随流释放时减少活跃流计数
+fn metered_body<S>(stream: S) -> Body
+where
+    S: Stream<Item = Result<Bytes, std::io::Error>> + Send + 'static,
+{
+    let stream_guard = PROXY_METRICS.start_stream();
+    Body::from_stream(stream.map(move |result| {
+        let _ = &stream_guard;
+        if let Ok(chunk) = &result {
+            PROXY_METRICS.record_bytes(chunk.len() as u64);
+        }
+        result
+    }))
+}
+
+/// 解析 Range 请求头（仅支持单个 `bytes=start-[end]` 区间）
+pub(crate) fn parse_range(range: &str) -> Option<(u64, Option<u64>)> {
+    let spec = range.trim().strip_prefix("bytes=")?;
+    if spec.contains(',') {
+        return None;
+    }
+    let (start, end) = spec.split_once('-')?;
+    let start = start.trim().parse::<u64>().ok()?;
+    let end = match end.trim() {
+        "" => None,
+        end => Some(end.parse::<u64>().ok()?),
+    };
+    // 结束位置在起点之前的区间无效，交给上游处理
+    if end.is_some_and(|end| end < start) {
+        return None;
+    }
+    Some((start, end))
+}
+
+/// 使用预取的开头数据响应请求（缓存之外的部分继续向上游请求）
+///
+/// 请求区间不在缓存范围内、或上游返回的区间与缓存不一致时返回 None，由调用方直接请求上游
+async fn serve_from_prefix(
+    url: &str,
+    prefix: Arc<audio_cache::CachedPrefix>,
+    range: Option<&str>,
+) -> Option<Response<Body>> {
+    let total = prefix.total_length;
+    let cached_len = prefix.data.len() as u64;
+    let (start, end) = match range {
+        Some(range) => parse_range(range)?,
+        None => (0, None),
+    };
+    let last_byte = total.checked_sub(1)?;
+    let end = end.unwrap_or(last_byte).min(last_byte);
+    if start >= cached_len || start > end {
+        return None;
+    }
+    
+    let cached_end = end.min(cached_len - 1);
+    let head = prefix.data.slice(start as usize..=cached_end as usize);
+    let head_stream = futures::stream::once(async move { Ok::<_, std::io::Error>(head) });
+    
+    let body = if cached_end == end {
+        metered_body(head_stream)
+    } else {
+        // 缓存之后的部分需要向上游请求，响应头发出前先确认上游返回的正是缓存之后的区间，
+        // 否则（文件已变化、上游不支持 Range 等）丢弃缓存，由调用方按未命中处理
+        let rest_range = format!("bytes={}-{}", cached_len, end);
+        let rest = match fetch_upstream(url, Some(&rest_range), reqwest::Method::GET).await {
+            Ok(response) => response,
+            Err(_) => return None,
+        };
+        if content_range_of(&rest) != Some((cached_len, total)) {
+            eprintln!("[Proxy] 上游区间与预取缓存不一致，丢弃缓存: {}", url);
+            audio_cache::remove(url);
+            return None;
+        }
+        let rest_stream = rest.bytes_stream().map(|result| result.map_err(std::io::Error::other));
+        metered_body(head_stream.chain(rest_stream))
+    };
+    
+    range_response_builder(&prefix.content_type, start, end, total, range.is_some())
+        .body(body)
+        .ok()
+}
+
+/// 解析 206 响应的起始字节和文件总长度（其他状态码返回 None）
+fn content_range_of(response: &reqwest::Response) -> Option<(u64, u64)> {
+    if response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+        return None;
+    }
+    let content_range = response.headers()
+        .get("content-range")
+        .and_then(|v| v.to_str().ok())?;
+    let (range, total) = content_range.trim().strip_prefix("bytes ")?.split_once('/')?;
+    let (range_start, _) = range.split_once('-')?;
+    Some((range_start.parse().ok()?, total.parse().ok()?))
+}
+
+/// 构建区间响应头（partial 为 true 时返回 206 和 Content-Range）
+pub(crate) fn range_response_builder(
+    content_type: &str,
+    start: u64,
+    end: u64,
+    total: u64,
+    partial: bool,
+) -> axum::http::response::Builder {
+    let response_builder = Response::builder()
+        .header(header::CONTENT_TYPE, content_type)
+        .header(header::CONTENT_LENGTH, end - start + 1)
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+        .header(header::ACCESS_CONTROL_ALLOW_METHODS, PROXY_ALLOWED_METHODS)
+        .header(header::ACCESS_CONTROL_ALLOW_HEADERS, "Range");
+    if partial {
+        response_builder
+            .status(StatusCode::PARTIAL_CONTENT)
+            .header(header::CONTENT_RANGE, format!("bytes {}-{}/{}", start, end, total))
+    } else {
+        response_builder.status(StatusCode::OK)
+    }
+}
+
+/// 预取下一首音频的开头部分（后台低优先级执行，新的预取会取消上一次）
+///
+/// 传入 url 时直接预取；否则预取播放会话中该分 P 将要使用的版本
+pub async fn prefetch_audio(
+    url: Option<String>,
+    bvid: Option<String>,
+    cid: Option<u64>,
+) -> Result<(), String> {
+    if url.as_deref().unwrap_or("").is_empty() && (bvid.is_none() || cid.is_none()) {
+        return Err("需要提供 url 或 bvid 和 cid".to_string());
+    }
+    
+    let task = tokio::spawn(async move {
+        // 稍后开始，避免和当前曲目的首次缓冲争抢带宽
+        tokio::time::sleep(Duration::from_millis(AUDIO_PREFETCH_DELAY_MS)).await;
+        
+        let url = match (url, bvid, cid) {
+            (Some(url), _, _) if !url.is_empty() => url,
+            // 与之后生成的播放地址使用同一版本
+            (_, Some(bvid), Some(cid)) => {
+                let bandwidth = play_session::preferred_bandwidth(&bvid, cid);
+                match play_session::stream_url_for(&bvid, cid, bandwidth).await {
+                    Ok(url) => url,
+                    Err(e) => {
+                        eprintln!("[Proxy] 预取解析播放地址失败: {}", e);
+                        return;
+                    }
+                }
+            }
+            _ => return,
+        };
+        
+        if let Err(e) = prefetch_prefix(&url).await {
+            eprintln!("[Proxy] 预取失败: {}", e);
+        }
+    });
+    
+    let mut task_guard = PREFETCH_TASK.lock().await;
+    if let Some(previous) = task_guard.replace(task) {
+        previous.abort();
+    }
+    Ok(())
+}
+
+/// 下载音频开头部分并写入预取缓存
+async fn prefetch_prefix(url: &str) -> Result<(), String> {
+    check_upstream_url(url)?;
+    if audio_cache::contains(url) {
+        return Ok(());
+    }
+    
+    let range = format!("bytes=0-{}", AUDIO_PREFETCH_BYTES - 1);
+    let mut response = fetch_upstream(url, Some(&range), reqwest::Method::GET)
+        .await
+        .map_err(|(_, reason)| reason)?;
+    
+    let content_type = response.headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("audio/mp4")
+        .to_string();
+    
+    // 206 响应从 Content-Range 获取完整长度，200 响应直接使用 Content-Length
+    let total_length = if response.status().as_u16() == 206 {
+        response.headers()
+            .get("content-range")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.rsplit('/').next())
+            .and_then(|v| v.parse::<u64>().ok())
+    } else {
+        response.content_length()
+    }
+    .ok_or_else(|| "无法确定音频长度".to_string())?;
+    
+    let mut data = Vec::with_capacity(AUDIO_PREFETCH_BYTES as usize);
+    while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+        data.extend_from_slice(&chunk);
+        if data.len() as u64 >= AUDIO_PREFETCH_BYTES {
+            break;
+        }
+        // 低优先级：每个分块后让出调度
+        tokio::task::yield_now().await;
+    }
+    data.truncate(AUDIO_PREFETCH_BYTES as usize);
+    
+    audio_cache::insert(url, audio_cache::CachedPrefix {
+        data: Bytes::from(data),
+        total_length,
+        content_type,
+    });
+    Ok(())
+}
+
+/// 封面图片查询参数
+#[derive(Deserialize)]
+struct ImageQuery {
+    token: Option<String>,
+    /// 目标宽度
+    w: Option<u32>,
+    /// 目标高度
+    h: Option<u32>,
+}
+
+/// 处理封面图片请求（带 B 站请求头获取，可缩放，结果缓存到磁盘）
+async fn handle_image_request(
     Path(encoded_url): Path<String>,
-    request: Request,
-) -> Result<Response<Body>, StatusCode> {
-    let url = match urlencoding::decode(&encoded_url) {
+    Query(query): Query<ImageQuery>,
+) -> Result<Response<Body>, (StatusCode, String)> {
+    let token = query.token.as_deref().unwrap_or("");
+    let url = validate_request(token, &encoded_url)?;
+    let size = ImageSize::clamped(query.w, query.h);
+    
+    // 需要缩放时获取原图，避免在 B 站已压缩的小图上再次缩放
+    let source_url = if size.is_resize() {
+        image_cache::original_image_url(&url)
+    } else {
+        url.clone()
+    };
+    
+    let cache_url = source_url.clone();
+    let cached = tokio::task::spawn_blocking(move || image_cache::load(&cache_url, size))
+        .await
+        .ok()
+        .flatten();
+    
+    let image = match cached {
+        Some(image) => {
+            PROXY_METRICS.record_image_cache_hit();
+            image
+        }
+        None => {
+            PROXY_METRICS.record_image_cache_miss();
+            let image = fetch_image(&source_url, size).await?;
+            let cache_url = source_url.clone();
+            let to_store = image_cache::CachedImage {
+                data: image.data.clone(),
+                content_type: image.content_type.clone(),
+            };
+            tokio::task::spawn_blocking(move || {
+                if let Err(e) = image_cache::store(&cache_url, size, &to_store) {
+                    eprintln!("[Proxy] 写入图片缓存失败: {}", e);
+                }
+            });
+            image
+        }
+    };
+    
+    PROXY_METRICS.record_bytes(image.data.len() as u64);
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, image.content_type)
+        .header(header::CONTENT_LENGTH, image.data.len())
+        .header(
+            header::CACHE_CONTROL,
+            format!("public, max-age={}, immutable", IMAGE_CACHE_MAX_AGE_SECS),
+        )
+        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+        .body(Body::from(image.data))
+        .unwrap())
+}
+
+/// 从上游获取图片（按需缩放）
+async fn fetch_image(
+    url: &str,
+    size: ImageSize,
+) -> Result<image_cache::CachedImage, (StatusCode, String)> {
+    if let Err(reason) = check_upstream_url(url) {
+        return Err((StatusCode::FORBIDDEN, reason));
+    }
+    let mut response = fetch_upstream(url, None, reqwest::Method::GET).await?;
+    let content_type = response.headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("image/jpeg")
+        .to_string();
+    
+    let mut data = Vec::new();
+    while let Some(chunk) = response.chunk().await
+        .map_err(|e| (StatusCode::BAD_GATEWAY, e.to_string()))?
+    {
+        data.extend_from_slice(&chunk);
+        if data.len() > IMAGE_MAX_BYTES {
+            return Err((StatusCode::BAD_GATEWAY, "图片过大".to_string()));
+        }
+    }
+    
+    if !size.is_resize() {
+        return Ok(image_cache::CachedImage { data, content_type });
+    }
+    tokio::task::spawn_blocking(move || image_cache::resize(&data, size))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map_err(|e| (StatusCode::BAD_GATEWAY, e))
+}
+
+/// 播放地址查询参数
+#[derive(Deserialize)]
+struct PlayQuery {
+    /// 固定的码率（bit/s）
+    q: Option<u64>,
+}
+
+/// 指标查询参数
+#[derive(Deserialize)]
+struct MetricsQuery {
+    token: Option<String>,
+}
+
+/// 播放列表（需要携带访问令牌）
+async fn handle_playlist(Query(query): Query<MetricsQuery>) -> Response {
+    if !query.token.as_deref().is_some_and(is_valid_play_token) {
+        return (StatusCode::FORBIDDEN, "访问令牌无效").into_response();
+    }
+    let Some(base_url) = proxy_status().await.base_url else {
+        return (StatusCode::SERVICE_UNAVAILABLE, "代理服务器未运行").into_response();
+    };
+    match playlist::render(&base_url, &playlist::current_playlist()) {
+        Ok(content) => (
+            [(header::CONTENT_TYPE, "application/vnd.apple.mpegurl; charset=utf-8")],
+            content,
+        )
+            .into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+/// 健康检查
+async fn handle_health() -> Json<serde_json::Value> {
+    Json(serde_json::json!({
+        "status": "ok",
+        "uptimeSecs": proxy_status().await.uptime_secs,
+    }))
+}
+
+/// 运行指标（需要携带访问令牌）
+async fn handle_metrics(Query(query): Query<MetricsQuery>) -> Response {
+    if !query.token.as_deref().is_some_and(is_valid_token) {
+        return (StatusCode::FORBIDDEN, "访问令牌无效").into_response();
+    }
+    Json(proxy_metrics().await).into_response()
+}
+
+/// 获取代理运行指标
+pub async fn proxy_metrics() -> MetricsSnapshot {
+    PROXY_METRICS.snapshot(proxy_status().await.uptime_secs)
+}
+
+/// 构建 CORS 层（由其统一响应 OPTIONS 预检请求）
+fn cors_layer() -> CorsLayer {
+    CorsLayer::new()
+        .allow_origin(Any)
+        .allow_methods([Method::GET, Method::HEAD, Method::OPTIONS])
+        .allow_headers([header::RANGE])
+        .expose_headers([
+            header::CONTENT_LENGTH,
+            header::CONTENT_RANGE,
+            header::ACCEPT_RANGES,
+        ])
+        .max_age(Duration::from_secs(PROXY_CORS_MAX_AGE_SECS))
+}
+
+/// 校验访问令牌和上游地址（返回解码后的上游 URL）
+fn validate_request(token: &str, encoded_url: &str) -> Result<String, (StatusCode, String)> {
+    if !is_valid_token(token) {
+        return Err((StatusCode::FORBIDDEN, "访问令牌无效".to_string()));
+    }
+    PROXY_METRICS.record_request();
+    
+    let url = match urlencoding::decode(encoded_url) {
         Ok(url) => url.to_string(),
-        Err(_) => return Err(StatusCode::BAD_REQUEST),
+        Err(_) => return Err((StatusCode::BAD_REQUEST, "URL 解码失败".to_string())),
     };
     
-    let client = match get_http_client().await {
+    if let Err(reason) = check_upstream_url(&url) {
+        return Err((StatusCode::FORBIDDEN, reason));
+    }
+    Ok(url)
+}
+
+/// 向上游发起请求（转发 Range 请求头）
+async fn fetch_upstream(
+    url: &str,
+    range: Option<&str>,
+    method: reqwest::Method,
+) -> Result<reqwest::Response, (StatusCode, String)> {
+    let client = match UPSTREAM_CLIENT.as_ref() {
         Ok(c) => c,
-        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, e.clone())),
     };
     
-    // 构建请求，支持 Range 请求
-    let mut req_builder = add_bilibili_headers(client.get(&url));
-    
-    // 转发 Range 请求头
-    if let Some(range) = request.headers().get("range") {
-        if let Ok(range_str) = range.to_str() {
+    let send = |method: reqwest::Method| {
+        // 构建请求，支持 Range 请求
+        let mut req_builder = add_bilibili_headers(client.request(method, url));
+        
+        // 转发 Range 请求头
+        if let Some(range_str) = range {
             req_builder = req_builder.header("Range", range_str);
         }
+        req_builder.send()
+    };
+    
+    let started_at = Instant::now();
+    let mut result = send(method.clone()).await;
+    
+    // 部分 CDN 节点不支持 HEAD，回退为 GET（只读取响应头）
+    if method == reqwest::Method::HEAD {
+        if let Ok(r) = &result {
+            let status = r.status().as_u16();
+            if status == 405 || status == 501 {
+                result = send(reqwest::Method::GET).await;
+            }
+        }
     }
     
-    let response = match req_builder.send().await {
+    let response = match result {
         Ok(r) => r,
         Err(e) => {
             eprintln!("[Proxy] 请求失败: {:?}", e);
-            return Err(StatusCode::BAD_GATEWAY);
+            PROXY_METRICS.record_upstream_error("network");
+            access_log::record_upstream(url, None);
+            access_log::record_error(e.to_string());
+            return Err((StatusCode::BAD_GATEWAY, "上游请求失败".to_string()));
         }
     };
     
+    PROXY_METRICS.record_ttfb(started_at.elapsed());
+    
     let reqwest_status = response.status();
     let status_u16 = reqwest_status.as_u16();
+    access_log::record_upstream(url, Some(status_u16));
     
     // 检查状态码
     if !reqwest_status.is_success() && status_u16 != 206 {
-        // 403 是链接过期的正常情况，前端会自动刷新，不打印日志
+        PROXY_METRICS.record_upstream_error(status_u16.to_string());
+        // 403 是链接过期的正常情况，前端会自动刷新，不打印日志（访问日志中仍有记录）
         if status_u16 != 403 {
             eprintln!("[Proxy] B站返回错误状态码: {}", status_u16);
         }
-        return Err(StatusCode::BAD_GATEWAY);
+        return Err((StatusCode::BAD_GATEWAY, format!("上游返回错误状态码: {}", status_u16)));
     }
     
+    Ok(response)
+}
+
+/// 根据上游响应构建代理响应头
+fn build_response_headers(response: &reqwest::Response) -> axum::http::response::Builder {
+    let status_u16 = response.status().as_u16();
+    
     // 转换状态码
     let axum_status = StatusCode::from_u16(status_u16)
         .unwrap_or(StatusCode::OK);
     
-    // 先保存所有需要的响应头信息（在移动 response 之前）
     let content_type = response.headers()
         .get("content-type")
         .and_then(|v| v.to_str().ok())
@@ -125,33 +989,13 @@ async fn handle_proxy_request(
         .and_then(|v| v.to_str().ok())
         .map(|s| s.to_string());
     
-    // 保存其他响应头（排除 Content-Type，因为我们会单独设置）
-    let mut headers_to_copy = Vec::new();
-    for (key, value) in response.headers() {
-        let key_str = key.as_str();
-        // 跳过 Content-Type, Content-Length, Content-Range（我们会单独处理）
-        if key_str != "content-type" && key_str != "content-length" && key_str != "content-range" {
-            if let Ok(header_name) = HeaderName::from_bytes(key_str.as_bytes()) {
-                if let Ok(value_str) = value.to_str() {
-                    if let Ok(header_value) = HeaderValue::from_str(value_str) {
-                        headers_to_copy.push((header_name, header_value));
-                    }
-                }
-            }
-        }
-    }
-    
-    // 将响应体转换为流（这会移动 response）
-    let stream = response.bytes_stream()
-        .map(|result| result.map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err)));
-    
     // 构建响应
     let mut response_builder = Response::builder()
         .status(axum_status)
         .header(header::CONTENT_TYPE, content_type)
         .header(header::ACCEPT_RANGES, "bytes")
         .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
-        .header(header::ACCESS_CONTROL_ALLOW_METHODS, "GET, HEAD, OPTIONS")
+        .header(header::ACCESS_CONTROL_ALLOW_METHODS, PROXY_ALLOWED_METHODS)
         .header(header::ACCESS_CONTROL_ALLOW_HEADERS, "Range");
     
     // 添加 Content-Length（如果存在）
@@ -168,12 +1012,44 @@ async fn handle_proxy_request(
         }
     }
     
-    // 添加保存的响应头
-    for (header_name, header_value) in headers_to_copy {
-        response_builder = response_builder.header(header_name, header_value);
+    // 复制其他响应头（跳过 Content-Type, Content-Length, Content-Range，我们会单独处理）
+    for (key, value) in response.headers() {
+        let key_str = key.as_str();
+        if key_str != "content-type" && key_str != "content-length" && key_str != "content-range" {
+            if let Ok(header_name) = HeaderName::from_bytes(key_str.as_bytes()) {
+                if let Ok(value_str) = value.to_str() {
+                    if let Ok(header_value) = HeaderValue::from_str(value_str) {
+                        response_builder = response_builder.header(header_name, header_value);
+                    }
+                }
+            }
+        }
+    }
+    
+    response_builder
+}
+
+/// 代理封面图片（返回代理 URL，可指定缩放尺寸）
+pub async fn proxy_image(url: String, width: Option<u32>, height: Option<u32>) -> Result<String, String> {
+    if url.is_empty() {
+        return Err("URL 为空".to_string());
     }
     
-    Ok(response_builder.body(Body::from_stream(stream)).unwrap())
+    let base_url = proxy_base_url().await?;
+    
+    let mut proxy_url = format!(
+        "{}/image/{}?token={}",
+        base_url,
+        urlencoding::encode(&url),
+        PROXY_ACCESS_TOKEN.as_str()
+    );
+    if let Some(width) = width {
+        proxy_url.push_str(&format!("&w={}", width));
+    }
+    if let Some(height) = height {
+        proxy_url.push_str(&format!("&h={}", height));
+    }
+    Ok(proxy_url)
 }
 
 /// 代理音频文件（返回代理 URL，支持流式播放）
@@ -183,9 +1059,89 @@ pub async fn proxy_audio(url: String) -> Result<String, String> {
     }
     
     // 启动代理服务器（如果还没启动）
-    let port = start_proxy_server().await?;
+    let base_url = proxy_base_url().await?;
     
-    // 返回代理 URL
+    // 返回代理 URL（携带本次会话的访问令牌）
     let encoded_url = urlencoding::encode(&url);
-    Ok(format!("http://127.0.0.1:{}/proxy/{}", port, encoded_url))
+    Ok(format!(
+        "{}/proxy/{}/{}",
+        base_url,
+        PROXY_ACCESS_TOKEN.as_str(),
+        encoded_url
+    ))
+}
+
+/// 获取按 bvid 和 cid 播放的代理 URL（重新打开播放会话，码率从 quality 指定的版本开始）
+pub async fn proxy_play_url(bvid: String, cid: u64, quality: Option<String>) -> Result<String, String> {
+    if bvid.is_empty() {
+        return Err("bvid 为空".to_string());
+    }
+    
+    // 码率固定在地址中，之后降低码率时需要重新获取地址
+    let bandwidth = play_session::open_session(&bvid, cid, quality.as_deref()).await?;
+    let base_url = proxy_base_url().await?;
+    Ok(format!(
+        "{}/play/{}/{}/{}?q={}",
+        base_url,
+        PROXY_ACCESS_TOKEN.as_str(),
+        urlencoding::encode(&bvid),
+        cid,
+        bandwidth
+    ))
+}
+
+/// 设置代理提供的播放列表（返回 `/playlist.m3u8` 地址，可直接在外部播放器中打开）
+pub async fn proxy_playlist_url(tracks: Vec<PlaylistTrack>) -> Result<String, String> {
+    playlist::set_playlist(tracks).await;
+    let base_url = proxy_base_url().await?;
+    Ok(format!(
+        "{}/playlist.m3u8?token={}",
+        base_url,
+        playlist::token()?
+    ))
+}
+
+/// 把播放列表写入 M3U8 文件（返回写入的路径）
+pub async fn export_playlist(tracks: Vec<PlaylistTrack>, path: String) -> Result<String, String> {
+    let base_url = proxy_base_url().await?;
+    playlist::export(&base_url, tracks, path).await
+}
+
+/// 更新代理端口和地址设置（代理正在运行时按新设置重启）
+pub async fn set_proxy_config(
+    preferred_port: Option<u16>,
+    fixed_port: bool,
+    ipv6: bool,
+) -> Result<ProxyConfig, String> {
+    proxy_config::set(preferred_port, fixed_port, ipv6)?;
+    if proxy_status().await.running {
+        restart_proxy_server().await?;
+    }
+    Ok(proxy_config::get())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn parse_range_boundaries() {
+        assert_eq!(parse_range("bytes=0-"), Some((0, None)));
+        assert_eq!(parse_range("bytes=0-0"), Some((0, Some(0))));
+        assert_eq!(parse_range("bytes=100-199"), Some((100, Some(199))));
+        assert_eq!(parse_range(" bytes= 5 - 9 "), Some((5, Some(9))));
+        assert_eq!(
+            parse_range(&format!("bytes={}-", u64::MAX)),
+            Some((u64::MAX, None))
+        );
+        
+        // 只支持单个 start-[end] 区间，其余交给上游处理
+        assert_eq!(parse_range("bytes=10-9"), None);
+        assert_eq!(parse_range("bytes=-500"), None);
+        assert_eq!(parse_range("bytes=0-1,5-6"), None);
+        assert_eq!(parse_range("bytes=5"), None);
+        assert_eq!(parse_range("bytes=a-b"), None);
+        assert_eq!(parse_range("items=0-1"), None);
+        assert_eq!(parse_range(""), None);
+    }
 }
@@ -0,0 +1,95 @@
+//! 代理配置模块
+//! 
+//! 保存代理的端口和地址设置，以及上次实际绑定的地址（持久化到应用数据目录），
+//! 使写入磁盘的播放列表等外部地址在重启后保持不变
+
+use crate::paths;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::net::SocketAddr;
+use std::sync::RwLock;
+
+const CONFIG_FILE: &str = "proxy.json";
+
+/// 代理配置
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProxyConfig {
+    /// 优先使用的端口（不设置时沿用上次的端口）
+    #[serde(default)]
+    pub preferred_port: Option<u16>,
+    /// 只使用优先端口，被占用时启动失败而不是改用随机端口
+    #[serde(default)]
+    pub fixed_port: bool,
+    /// 绑定 IPv6 回环地址 `[::1]`（不可用时回退到 127.0.0.1）
+    #[serde(default)]
+    pub ipv6: bool,
+    /// 上次实际绑定的地址（由代理启动时记录）
+    #[serde(default)]
+    pub last_address: Option<SocketAddr>,
+}
+
+lazy_static! {
+    static ref PROXY_CONFIG: RwLock<Option<ProxyConfig>> = RwLock::new(None);
+}
+
+/// 获取代理配置（首次调用时从数据目录读取）
+pub fn get() -> ProxyConfig {
+    if let Some(config) = PROXY_CONFIG.read().ok().and_then(|c| c.clone()) {
+        return config;
+    }
+    
+    let config = paths::data_file(CONFIG_FILE)
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str::<ProxyConfig>(&content).ok())
+        .unwrap_or_default();
+    if let Ok(mut cached) = PROXY_CONFIG.write() {
+        *cached = Some(config.clone());
+    }
+    config
+}
+
+/// 保存代理配置
+fn save(config: &ProxyConfig) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(config).map_err(|e| e.to_string())?;
+    fs::write(paths::data_file(CONFIG_FILE)?, content)
+        .map_err(|e| format!("保存代理配置失败: {}", e))?;
+    if let Ok(mut cached) = PROXY_CONFIG.write() {
+        *cached = Some(config.clone());
+    }
+    Ok(())
+}
+
+/// 更新端口和地址设置（返回更新后的配置）
+pub fn set(preferred_port: Option<u16>, fixed_port: bool, ipv6: bool) -> Result<ProxyConfig, String> {
+    let preferred_port = preferred_port.filter(|port| *port != 0);
+    if fixed_port && preferred_port.is_none() {
+        return Err("固定端口需要指定端口号".to_string());
+    }
+    
+    let config = ProxyConfig {
+        preferred_port,
+        fixed_port,
+        ipv6,
+        ..get()
+    };
+    save(&config)?;
+    Ok(config)
+}
+
+/// 记录实际绑定的地址（与上次相同时不写文件）
+pub fn record_address(addr: SocketAddr) {
+    let config = get();
+    if config.last_address == Some(addr) {
+        return;
+    }
+    let config = ProxyConfig {
+        last_address: Some(addr),
+        ..config
+    };
+    if let Err(e) = save(&config) {
+        eprintln!("[Proxy] {}", e);
+    }
+}
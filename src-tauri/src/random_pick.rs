@@ -0,0 +1,277 @@
+//! 随机选取模块
+//! 
+//! “纲一下”的选取逻辑：按电台的关键词权重和页码范围随机搜索，排除最近播放过的视频，打乱后逐个获取详情校验，
+//! 按筛选条件排除不合适的视频，返回包含分 P 和播放地址的完整条目。播放历史持久化到应用数据目录，桌面端和 Android 共用
+
+use crate::bilibili::{self, ApiError, SearchVideo, VideoInfo};
+use crate::constants::{PLAYED_HISTORY_SIZE, RANDOM_PICK_PREFERRED_DURATION};
+use crate::paths;
+use crate::pick_filter::{self, PickFilter};
+use crate::proxy;
+use crate::stations::{self, Station, STATION_DAN, STATION_DEFAULT, STATION_DUI};
+use lazy_static::lazy_static;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs;
+use std::sync::Mutex;
+
+const HISTORY_FILE: &str = "played_history.json";
+
+/// 相声类型
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum GangType {
+    /// 单口相声
+    Dan,
+    /// 对口相声
+    Dui,
+    /// 不限（使用已选择的电台，未选择时使用自定义关键词或默认关键词）
+    All,
+}
+
+/// 分 P（与前端 VideoItem 结构一致）
+#[derive(Debug, Serialize, Clone)]
+pub struct PickedPage {
+    pub bvid: String,
+    pub title: String,
+    pub pic: String,
+    /// 时长（秒）
+    pub duration: u64,
+    pub cid: u64,
+}
+
+/// 随机选取的条目
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PickedItem {
+    pub bvid: String,
+    pub title: String,
+    pub pic: String,
+    /// 第一个分 P 的时长（秒）
+    pub duration: u64,
+    /// 第一个分 P 的 cid
+    pub cid: u64,
+    /// 所有分 P（只有一个分 P 时为空）
+    pub pages: Vec<PickedPage>,
+    /// 第一个分 P 的代理播放地址
+    pub audio_url: String,
+}
+
+lazy_static! {
+    /// 最近播放过的 bvid（从旧到新，首次使用时从数据目录读取）
+    static ref PLAYED_HISTORY: Mutex<Option<VecDeque<String>>> = Mutex::new(None);
+}
+
+/// 读取播放历史文件
+fn load_history() -> VecDeque<String> {
+    paths::data_file(HISTORY_FILE)
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// 在播放历史上执行操作（首次调用时加载）
+fn with_history<R>(f: impl FnOnce(&mut VecDeque<String>) -> R) -> Option<R> {
+    let mut guard = PLAYED_HISTORY.lock().ok()?;
+    let history = guard.get_or_insert_with(load_history);
+    Some(f(history))
+}
+
+/// 保存播放历史
+fn save_history(history: &VecDeque<String>) {
+    let result = serde_json::to_string(history)
+        .map_err(|e| e.to_string())
+        .and_then(|content| {
+            fs::write(paths::data_file(HISTORY_FILE)?, content).map_err(|e| e.to_string())
+        });
+    if let Err(e) = result {
+        eprintln!("[RandomPick] 保存播放历史失败: {}", e);
+    }
+}
+
+/// 记录播放过的视频（超出条数时丢弃最旧的）
+fn record_played(bvid: &str) {
+    with_history(|history| {
+        history.retain(|played| played != bvid);
+        history.push_back(bvid.to_string());
+        while history.len() > PLAYED_HISTORY_SIZE {
+            history.pop_front();
+        }
+        save_history(history);
+    });
+}
+
+fn is_played(bvid: &str) -> bool {
+    with_history(|history| history.iter().any(|played| played == bvid)).unwrap_or(false)
+}
+
+/// 清空播放历史
+pub fn clear_history() -> Result<(), String> {
+    with_history(|history| history.clear());
+    match paths::data_file(HISTORY_FILE).map(fs::remove_file) {
+        Ok(Err(e)) if e.kind() != std::io::ErrorKind::NotFound => Err(format!("清空播放历史失败: {}", e)),
+        _ => Ok(()),
+    }
+}
+
+/// 选取使用的电台
+///
+/// 指定电台时直接使用；否则单口、对口使用内置电台，不限时依次使用已选择的电台、自定义关键词和默认电台
+fn station_for(
+    gang_type: GangType,
+    station_id: Option<&str>,
+    custom_keywords: &[String],
+) -> Result<Station, ApiError> {
+    if let Some(id) = station_id {
+        return stations::get(id).ok_or_else(|| format!("电台不存在: {}", id).into());
+    }
+    let station = match gang_type {
+        GangType::Dan => stations::get(STATION_DAN),
+        GangType::Dui => stations::get(STATION_DUI),
+        GangType::All => stations::selected().or_else(|| {
+            let custom: Vec<String> = custom_keywords
+                .iter()
+                .map(|keyword| keyword.trim().to_string())
+                .filter(|keyword| !keyword.is_empty())
+                .collect();
+            if custom.is_empty() {
+                stations::get(STATION_DEFAULT)
+            } else {
+                Some(Station::from_keywords("custom", "自定义", &custom))
+            }
+        }),
+    };
+    station.ok_or_else(|| "没有可用的电台".to_string().into())
+}
+
+/// 搜索一页并保留同时符合电台条件和筛选条件的视频
+async fn search_accepted(
+    station: &Station,
+    filter: &PickFilter,
+    keyword: &str,
+    page: u32,
+    duration: Option<u8>,
+) -> Result<Vec<SearchVideo>, ApiError> {
+    Ok(bilibili::search_videos(keyword, page, duration, station.order)
+        .await?
+        .videos
+        .into_iter()
+        .filter(|v| station.accepts(v) && filter.accepts(v))
+        .collect())
+}
+
+/// 搜索符合条件的候选视频
+///
+/// 电台设置了时长范围时按范围筛选；否则优先 30-60 分钟，没有结果时不限时长
+async fn search_candidates(
+    station: &Station,
+    filter: &PickFilter,
+    keyword: &str,
+    page: u32,
+) -> Result<Vec<SearchVideo>, ApiError> {
+    if station.has_duration_range() {
+        return search_accepted(station, filter, keyword, page, station.duration_filter()).await;
+    }
+    let accepted = search_accepted(station, filter, keyword, page, Some(RANDOM_PICK_PREFERRED_DURATION)).await?;
+    if !accepted.is_empty() {
+        return Ok(accepted);
+    }
+    search_accepted(station, filter, keyword, page, None).await
+}
+
+/// 获取详情和播放地址，生成完整条目
+async fn resolve(bvid: &str, quality: Option<&str>) -> Result<PickedItem, ApiError> {
+    let info: VideoInfo = bilibili::get_video_info(bvid).await?;
+    let (duration, cid) = match info.pages.first() {
+        Some(first) => (first.duration, first.cid),
+        None => (info.duration, info.cid),
+    };
+    let pages = if info.pages.len() > 1 {
+        info.pages
+            .iter()
+            .map(|page| PickedPage {
+                bvid: info.bvid.clone(),
+                title: page.part.clone(),
+                pic: info.pic.clone(),
+                duration: page.duration,
+                cid: page.cid,
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let audio_url = proxy::proxy_play_url(info.bvid.clone(), cid, quality.map(|q| q.to_string())).await?;
+    
+    Ok(PickedItem {
+        bvid: info.bvid,
+        title: info.title,
+        pic: info.pic,
+        duration,
+        cid,
+        pages,
+        audio_url,
+    })
+}
+
+/// 打乱候选视频：有没播放过的视频时只保留这些，都播放过时使用全部结果
+fn unplayed_first(videos: Vec<SearchVideo>) -> Vec<SearchVideo> {
+    let unplayed: Vec<SearchVideo> = videos.iter().filter(|v| !is_played(&v.bvid)).cloned().collect();
+    let mut candidates = if unplayed.is_empty() { videos } else { unplayed };
+    candidates.shuffle(&mut rand::thread_rng());
+    candidates
+}
+
+/// 依次尝试候选视频，返回第一个可以播放的（触发风控时立即返回错误）
+async fn first_playable(candidates: &[SearchVideo], quality: Option<&str>) -> Result<Option<PickedItem>, ApiError> {
+    for video in candidates {
+        match resolve(&video.bvid, quality).await {
+            Ok(item) => {
+                record_played(&item.bvid);
+                return Ok(Some(item));
+            }
+            Err(e @ ApiError::RiskControl { .. }) => return Err(e),
+            Err(e) => eprintln!("[RandomPick] 跳过 {}: {}", video.bvid, e),
+        }
+    }
+    Ok(None)
+}
+
+/// 随机选取一个相声视频
+pub async fn pick(
+    gang_type: GangType,
+    station_id: Option<&str>,
+    custom_keywords: &[String],
+    quality: Option<&str>,
+) -> Result<PickedItem, ApiError> {
+    let station = station_for(gang_type, station_id, custom_keywords)?;
+    let filter = pick_filter::get();
+    let (keyword, page) = {
+        let mut rng = rand::thread_rng();
+        let (min_page, max_page) = station.page_range();
+        match station.choose_keyword(&mut rng) {
+            Some(keyword) => (keyword.to_string(), rng.gen_range(min_page..=max_page)),
+            None => return Err("没有可用的关键词".to_string().into()),
+        }
+    };
+    
+    let videos = search_candidates(&station, &filter, &keyword, page).await?;
+    if let Some(item) = first_playable(&unplayed_first(videos), quality).await? {
+        return Ok(item);
+    }
+    
+    // 回退：依次尝试其他关键词，页码同样在电台的页码范围内随机选取
+    let (min_page, max_page) = station.page_range();
+    for fallback in station.keywords.iter().filter(|k| k.keyword != keyword && k.weight > 0.0) {
+        let page = rand::thread_rng().gen_range(min_page..=max_page);
+        let videos =
+            search_accepted(&station, &filter, &fallback.keyword, page, station.duration_filter()).await?;
+        if let Some(item) = first_playable(&unplayed_first(videos), quality).await? {
+            return Ok(item);
+        }
+    }
+    
+    Err("没有找到可以播放的视频".to_string().into())
+}
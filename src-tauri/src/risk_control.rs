@@ -0,0 +1,165 @@
+//! 风控应对模块
+//! 
+//! 识别 B 站的风控响应（HTTP 412、code -412/-352），触发后全局冷却一段时间（连续触发时加倍），
+//! 平时用令牌桶控制请求节奏；冷却结束后的第一个请求可先重新获取 buvid 和 bili_ticket
+
+use crate::constants::{
+    RISK_BUCKET_CAPACITY, RISK_BUCKET_REFILL_PER_SEC, RISK_COOLDOWN_BASE_SECS, RISK_COOLDOWN_MAX_SECS,
+};
+use crate::events;
+use crate::http_client;
+use lazy_static::lazy_static;
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// 令牌桶与冷却状态
+struct Limiter {
+    tokens: f64,
+    last_refill: Instant,
+    cooldown_until: Option<Instant>,
+    /// 连续触发风控的次数（请求成功后清零）
+    strikes: u32,
+    /// 冷却结束后是否需要先重新获取 Cookie
+    needs_rewarm: bool,
+    rewarm_enabled: bool,
+}
+
+/// 风控状态（返回给前端）
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RiskControlStatus {
+    pub cooling_down: bool,
+    pub retry_after_secs: u64,
+    pub strikes: u32,
+    pub rewarm_enabled: bool,
+}
+
+/// 触发风控事件
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct RiskControlEvent {
+    code: i64,
+    retry_after_secs: u64,
+}
+
+lazy_static! {
+    static ref LIMITER: Mutex<Limiter> = Mutex::new(Limiter {
+        tokens: RISK_BUCKET_CAPACITY,
+        last_refill: Instant::now(),
+        cooldown_until: None,
+        strikes: 0,
+        needs_rewarm: false,
+        rewarm_enabled: true,
+    });
+}
+
+/// 是否为风控响应码
+pub fn is_risk_code(code: i64) -> bool {
+    matches!(code, -412 | -352)
+}
+
+/// 等待发送请求的许可（冷却中时立即返回剩余秒数）
+///
+/// 冷却结束后的第一个请求会先重新获取 Cookie（可通过设置关闭）
+pub async fn acquire() -> Result<(), u64> {
+    let rewarm = take_token().await?;
+    if rewarm {
+        http_client::rewarm_cookies().await;
+    }
+    Ok(())
+}
+
+/// 从令牌桶中取出一个令牌（返回是否需要重新获取 Cookie）
+async fn take_token() -> Result<bool, u64> {
+    loop {
+        let wait = {
+            let mut limiter = match LIMITER.lock() {
+                Ok(limiter) => limiter,
+                Err(_) => return Ok(false),
+            };
+            let now = Instant::now();
+            if let Some(until) = limiter.cooldown_until {
+                if until > now {
+                    return Err((until - now).as_secs().max(1));
+                }
+                limiter.cooldown_until = None;
+            }
+            
+            let elapsed = now.duration_since(limiter.last_refill).as_secs_f64();
+            limiter.tokens = (limiter.tokens + elapsed * RISK_BUCKET_REFILL_PER_SEC).min(RISK_BUCKET_CAPACITY);
+            limiter.last_refill = now;
+            if limiter.tokens >= 1.0 {
+                limiter.tokens -= 1.0;
+                let rewarm = limiter.needs_rewarm && limiter.rewarm_enabled;
+                limiter.needs_rewarm = false;
+                return Ok(rewarm);
+            }
+            (1.0 - limiter.tokens) / RISK_BUCKET_REFILL_PER_SEC
+        };
+        tokio::time::sleep(Duration::from_secs_f64(wait)).await;
+    }
+}
+
+/// 记录一次风控（返回冷却秒数）
+pub fn report_blocked(code: i64) -> u64 {
+    let cooldown = match LIMITER.lock() {
+        Ok(mut limiter) => {
+            limiter.strikes += 1;
+            let cooldown = RISK_COOLDOWN_BASE_SECS
+                .saturating_mul(1 << (limiter.strikes - 1).min(16))
+                .min(RISK_COOLDOWN_MAX_SECS);
+            limiter.cooldown_until = Some(Instant::now() + Duration::from_secs(cooldown));
+            limiter.tokens = 0.0;
+            limiter.needs_rewarm = true;
+            cooldown
+        }
+        Err(_) => RISK_COOLDOWN_BASE_SECS,
+    };
+    eprintln!("[RiskControl] 触发风控 ({})，冷却 {} 秒", code, cooldown);
+    events::emit("risk-control", RiskControlEvent {
+        code,
+        retry_after_secs: cooldown,
+    });
+    cooldown
+}
+
+/// 记录一次正常响应（清除连续触发计数）
+pub fn report_ok() {
+    if let Ok(mut limiter) = LIMITER.lock() {
+        limiter.strikes = 0;
+    }
+}
+
+/// 获取风控状态
+pub fn status() -> RiskControlStatus {
+    let limiter = match LIMITER.lock() {
+        Ok(limiter) => limiter,
+        Err(_) => {
+            return RiskControlStatus {
+                cooling_down: false,
+                retry_after_secs: 0,
+                strikes: 0,
+                rewarm_enabled: true,
+            }
+        }
+    };
+    let remaining = limiter
+        .cooldown_until
+        .map(|until| until.saturating_duration_since(Instant::now()).as_secs())
+        .unwrap_or(0);
+    RiskControlStatus {
+        cooling_down: remaining > 0,
+        retry_after_secs: remaining,
+        strikes: limiter.strikes,
+        rewarm_enabled: limiter.rewarm_enabled,
+    }
+}
+
+/// 设置冷却结束后是否重新获取 Cookie
+pub fn set_rewarm_enabled(enabled: bool) -> RiskControlStatus {
+    if let Ok(mut limiter) = LIMITER.lock() {
+        limiter.rewarm_enabled = enabled;
+    }
+    status()
+}
@@ -0,0 +1,338 @@
+//! 电台模块
+//! 
+//! 电台是命名的关键词池：每个关键词带权重，并可设置时长范围、搜索排序、页码范围和 UP 主黑白名单。
+//! 内置单口、对口和默认电台，用户电台从应用数据目录的 stations.toml（或 stations.json）读取，
+//! 同 id 的用户电台覆盖内置电台
+
+use crate::bilibili::{SearchOrder, SearchVideo};
+use crate::constants::{DANKOU_KEYWORDS, DEFAULT_KEYWORDS, DUIKOU_KEYWORDS, RANDOM_PICK_MAX_PAGE};
+use crate::paths;
+use lazy_static::lazy_static;
+use rand::distributions::WeightedIndex;
+use rand::prelude::Distribution;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::sync::RwLock;
+
+const STATIONS_TOML_FILE: &str = "stations.toml";
+const STATIONS_JSON_FILE: &str = "stations.json";
+const SELECTED_STATION_FILE: &str = "selected_station";
+
+/// 内置电台 id
+pub const STATION_DAN: &str = "dan";
+pub const STATION_DUI: &str = "dui";
+pub const STATION_DEFAULT: &str = "default";
+
+/// 带权重的关键词（配置中可直接写字符串，权重为 1）
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(from = "KeywordEntry")]
+pub struct WeightedKeyword {
+    pub keyword: String,
+    pub weight: f64,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum KeywordEntry {
+    Plain(String),
+    Weighted {
+        keyword: String,
+        #[serde(default = "default_weight")]
+        weight: f64,
+    },
+}
+
+impl From<KeywordEntry> for WeightedKeyword {
+    fn from(entry: KeywordEntry) -> Self {
+        match entry {
+            KeywordEntry::Plain(keyword) => WeightedKeyword { keyword, weight: 1.0 },
+            KeywordEntry::Weighted { keyword, weight } => WeightedKeyword { keyword, weight },
+        }
+    }
+}
+
+fn default_weight() -> f64 {
+    1.0
+}
+
+fn default_min_page() -> u32 {
+    1
+}
+
+fn default_max_page() -> u32 {
+    RANDOM_PICK_MAX_PAGE
+}
+
+/// 电台定义（配置文件中的字段同时支持 camelCase 和 snake_case）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Station {
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    pub keywords: Vec<WeightedKeyword>,
+    /// 时长范围（秒）
+    #[serde(default, alias = "min_duration")]
+    pub min_duration: Option<u64>,
+    #[serde(default, alias = "max_duration")]
+    pub max_duration: Option<u64>,
+    #[serde(default)]
+    pub order: SearchOrder,
+    /// 随机选取的搜索页码范围
+    #[serde(default = "default_min_page", alias = "min_page")]
+    pub min_page: u32,
+    #[serde(default = "default_max_page", alias = "max_page")]
+    pub max_page: u32,
+    /// 只选择这些 UP 主的视频（名称或 mid，为空时不限制）
+    #[serde(default, alias = "allow_uploaders")]
+    pub allow_uploaders: Vec<String>,
+    /// 排除这些 UP 主的视频（名称或 mid）
+    #[serde(default, alias = "deny_uploaders")]
+    pub deny_uploaders: Vec<String>,
+}
+
+impl Station {
+    /// 由关键词列表创建电台（权重相同，其余条件使用默认值）
+    pub fn from_keywords(id: &str, name: &str, keywords: &[String]) -> Self {
+        Station {
+            id: id.to_string(),
+            name: name.to_string(),
+            keywords: keywords
+                .iter()
+                .map(|keyword| WeightedKeyword { keyword: keyword.clone(), weight: 1.0 })
+                .collect(),
+            min_duration: None,
+            max_duration: None,
+            order: SearchOrder::default(),
+            min_page: default_min_page(),
+            max_page: default_max_page(),
+            allow_uploaders: Vec::new(),
+            deny_uploaders: Vec::new(),
+        }
+    }
+    
+    fn builtin(id: &str, name: &str, keywords: &[&str]) -> Self {
+        let keywords: Vec<String> = keywords.iter().map(|keyword| keyword.to_string()).collect();
+        Self::from_keywords(id, name, &keywords)
+    }
+    
+    /// 有效的关键词（权重为正且不为空）
+    fn usable_keywords(&self) -> Vec<&WeightedKeyword> {
+        self.keywords
+            .iter()
+            .filter(|k| k.weight > 0.0 && !k.keyword.trim().is_empty())
+            .collect()
+    }
+    
+    /// 检查电台配置（错误信息中带上电台 id）
+    fn validate(&self) -> Result<(), String> {
+        if let (Some(min), Some(max)) = (self.min_duration, self.max_duration) {
+            if min > max {
+                return Err(format!("电台 {}: 最短时长不能大于最长时长", self.id));
+            }
+        }
+        if self.usable_keywords().is_empty() {
+            return Err(format!("电台 {}: 没有权重大于 0 的关键词", self.id));
+        }
+        Ok(())
+    }
+    
+    /// 按权重随机选择一个关键词（没有有效关键词时返回 None）
+    pub fn choose_keyword<R: rand::Rng>(&self, rng: &mut R) -> Option<&str> {
+        let keywords = self.usable_keywords();
+        let index = WeightedIndex::new(keywords.iter().map(|k| k.weight)).ok()?;
+        Some(keywords[index.sample(rng)].keyword.as_str())
+    }
+    
+    /// 随机选取使用的页码范围
+    pub fn page_range(&self) -> (u32, u32) {
+        let min = self.min_page.max(1);
+        (min, self.max_page.max(min))
+    }
+    
+    /// 能完全覆盖时长范围的 B 站时长筛选（1-4），跨多个区间或未设置范围时返回 None
+    pub fn duration_filter(&self) -> Option<u8> {
+        if self.min_duration.is_none() && self.max_duration.is_none() {
+            return None;
+        }
+        let min = self.min_duration.unwrap_or(0);
+        let max = self.max_duration.unwrap_or(u64::MAX);
+        [(1, 0, 600), (2, 600, 1800), (3, 1800, 3600), (4, 3600, u64::MAX)]
+            .iter()
+            .find(|(_, low, high)| min >= *low && max <= *high)
+            .map(|(filter, _, _)| *filter)
+    }
+    
+    pub fn has_duration_range(&self) -> bool {
+        self.min_duration.is_some() || self.max_duration.is_some()
+    }
+    
+    /// 搜索结果是否符合电台条件（时长范围、UP 主黑白名单）
+    pub fn accepts(&self, video: &SearchVideo) -> bool {
+        if self.min_duration.map(|min| video.duration < min).unwrap_or(false)
+            || self.max_duration.map(|max| video.duration > max).unwrap_or(false)
+        {
+            return false;
+        }
+        let matches = |uploader: &String| *uploader == video.author || *uploader == video.mid.to_string();
+        if self.deny_uploaders.iter().any(matches) {
+            return false;
+        }
+        self.allow_uploaders.is_empty() || self.allow_uploaders.iter().any(matches)
+    }
+}
+
+/// 电台配置文件
+#[derive(Debug, Deserialize)]
+struct StationFile {
+    #[serde(default)]
+    stations: Vec<Station>,
+}
+
+/// 电台列表及当前选择的电台
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StationList {
+    pub stations: Vec<Station>,
+    pub selected: Option<String>,
+}
+
+lazy_static! {
+    static ref STATIONS: RwLock<Option<StationList>> = RwLock::new(None);
+}
+
+fn builtin_stations() -> Vec<Station> {
+    vec![
+        Station::builtin(STATION_DEFAULT, "默认", DEFAULT_KEYWORDS),
+        Station::builtin(STATION_DAN, "单口相声", DANKOU_KEYWORDS),
+        Station::builtin(STATION_DUI, "对口相声", DUIKOU_KEYWORDS),
+    ]
+}
+
+/// 读取用户电台（优先 TOML，文件不存在时返回空列表）
+fn load_user_stations() -> Result<Vec<Station>, String> {
+    let toml_path = paths::data_file(STATIONS_TOML_FILE)?;
+    let json_path = paths::data_file(STATIONS_JSON_FILE)?;
+    let stations = if let Ok(content) = fs::read_to_string(&toml_path) {
+        toml::from_str::<StationFile>(&content)
+            .map_err(|e| format!("解析 {} 失败: {}", STATIONS_TOML_FILE, e))?
+            .stations
+    } else if let Ok(content) = fs::read_to_string(&json_path) {
+        serde_json::from_str::<StationFile>(&content)
+            .map_err(|e| format!("解析 {} 失败: {}", STATIONS_JSON_FILE, e))?
+            .stations
+    } else {
+        return Ok(Vec::new());
+    };
+    
+    // 没有 id 的电台在合并时跳过，不需要检查
+    for station in stations.iter().filter(|s| !s.id.trim().is_empty()) {
+        station.validate()?;
+    }
+    Ok(stations)
+}
+
+/// 合并内置电台和用户电台，读取上次选择的电台
+fn load() -> Result<StationList, String> {
+    let mut stations = builtin_stations();
+    for station in load_user_stations()? {
+        if station.id.trim().is_empty() {
+            continue;
+        }
+        match stations.iter_mut().find(|s| s.id == station.id) {
+            Some(existing) => *existing = station,
+            None => stations.push(station),
+        }
+    }
+    let selected = paths::data_file(SELECTED_STATION_FILE)
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .map(|id| id.trim().to_string())
+        .filter(|id| stations.iter().any(|s| s.id == *id));
+    Ok(StationList { stations, selected })
+}
+
+/// 获取电台列表（首次调用时读取，配置有误时只使用内置电台）
+pub fn list() -> StationList {
+    if let Some(list) = STATIONS.read().ok().and_then(|s| s.clone()) {
+        return list;
+    }
+    let list = load().unwrap_or_else(|e| {
+        eprintln!("[Stations] {}", e);
+        StationList {
+            stations: builtin_stations(),
+            selected: None,
+        }
+    });
+    if let Ok(mut cached) = STATIONS.write() {
+        *cached = Some(list.clone());
+    }
+    list
+}
+
+/// 重新读取电台配置（配置有误时返回错误，保留原有列表）
+pub fn reload() -> Result<StationList, String> {
+    let list = load()?;
+    if let Ok(mut cached) = STATIONS.write() {
+        *cached = Some(list.clone());
+    }
+    Ok(list)
+}
+
+/// 按 id 查找电台
+pub fn get(id: &str) -> Option<Station> {
+    list().stations.into_iter().find(|s| s.id == id)
+}
+
+/// 当前选择的电台
+pub fn selected() -> Option<Station> {
+    let list = list();
+    let id = list.selected?;
+    list.stations.into_iter().find(|s| s.id == id)
+}
+
+/// 选择电台（None 时取消选择，恢复使用自定义关键词）
+pub fn select(id: Option<String>) -> Result<StationList, String> {
+    let mut list = list();
+    let path = paths::data_file(SELECTED_STATION_FILE)?;
+    match id {
+        Some(id) => {
+            if !list.stations.iter().any(|s| s.id == id) {
+                return Err(format!("电台不存在: {}", id));
+            }
+            fs::write(&path, &id).map_err(|e| format!("保存电台选择失败: {}", e))?;
+            list.selected = Some(id);
+        }
+        None => {
+            let _ = fs::remove_file(&path);
+            list.selected = None;
+        }
+    }
+    if let Ok(mut cached) = STATIONS.write() {
+        *cached = Some(list.clone());
+    }
+    Ok(list)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn validate_rejects_empty_weights_and_inverted_duration() {
+        let mut station = Station::from_keywords("late-night", "深夜", &["郭德纲".to_string()]);
+        assert!(station.validate().is_ok());
+        
+        station.min_duration = Some(1800);
+        station.max_duration = Some(600);
+        assert_eq!(station.validate().unwrap_err(), "电台 late-night: 最短时长不能大于最长时长");
+        station.max_duration = Some(1800);
+        assert!(station.validate().is_ok());
+        
+        station.keywords[0].weight = 0.0;
+        station.keywords.push(WeightedKeyword { keyword: " ".to_string(), weight: 2.0 });
+        assert_eq!(station.validate().unwrap_err(), "电台 late-night: 没有权重大于 0 的关键词");
+        station.keywords.clear();
+        assert!(station.validate().is_err());
+    }
+}
@@ -0,0 +1,175 @@
+//! WBI 签名模块
+//! 
+//! 从 nav 接口获取并缓存 img_key/sub_key，为路径中带 `/wbi/` 的接口计算 `w_rid`/`wts` 签名。
+//! 密钥每天轮换，缓存超过有效期或签名被拒绝时重新获取
+
+use crate::constants::{BILIBILI_API_BASE, WBI_KEYS_TTL_SECS};
+use crate::http_client::{add_bilibili_headers, get_http_client};
+use lazy_static::lazy_static;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::Mutex;
+
+/// 混合密钥的字符重排表
+const MIXIN_KEY_ENC_TAB: [usize; 64] = [
+    46, 47, 18, 2, 53, 8, 23, 32, 15, 50, 10, 31, 58, 3, 45, 35, 27, 43, 5, 49, 33, 9, 42, 19, 29,
+    28, 14, 39, 12, 38, 41, 13, 37, 48, 7, 16, 24, 55, 40, 61, 26, 17, 0, 1, 60, 51, 30, 4, 22, 25,
+    54, 21, 56, 59, 6, 63, 57, 62, 11, 36, 20, 34, 44, 52,
+];
+
+/// 签名前需要从参数值中去除的字符
+const FILTERED_CHARS: &[char] = &['!', '\'', '(', ')', '*'];
+
+/// 缓存的混合密钥
+struct WbiKey {
+    mixin_key: String,
+    fetched_at: Instant,
+}
+
+lazy_static! {
+    static ref WBI_KEY: Mutex<Option<WbiKey>> = Mutex::new(None);
+}
+
+/// 接口是否需要 WBI 签名
+pub fn needs_signing(path: &str) -> bool {
+    path.contains("/wbi/")
+}
+
+/// 由 img_key 和 sub_key 计算混合密钥
+fn mixin_key(img_key: &str, sub_key: &str) -> String {
+    let raw: Vec<char> = format!("{}{}", img_key, sub_key).chars().collect();
+    MIXIN_KEY_ENC_TAB
+        .iter()
+        .filter_map(|&i| raw.get(i))
+        .take(32)
+        .collect()
+}
+
+/// 从密钥图片地址中取出密钥（文件名去掉扩展名）
+fn key_from_url(url: &str) -> Option<String> {
+    let name = url.rsplit('/').next()?;
+    let key = name.split('.').next()?;
+    (!key.is_empty()).then(|| key.to_string())
+}
+
+/// 从 nav 接口获取密钥（未登录时 code 为 -101，但仍会返回 wbi_img）
+async fn fetch_mixin_key() -> Result<String, String> {
+    let client = get_http_client().await?;
+    let body = add_bilibili_headers(client.get(format!("{}/x/web-interface/nav", BILIBILI_API_BASE)))
+        .send()
+        .await
+        .map_err(|e| format!("获取 WBI 密钥失败: {}", e))?
+        .text()
+        .await
+        .map_err(|e| format!("获取 WBI 密钥失败: {}", e))?;
+    let json: serde_json::Value = serde_json::from_str(&body)
+        .map_err(|e| format!("解析 WBI 密钥失败: {}", e))?;
+    
+    let wbi_img = &json["data"]["wbi_img"];
+    let img_key = wbi_img["img_url"].as_str().and_then(key_from_url);
+    let sub_key = wbi_img["sub_url"].as_str().and_then(key_from_url);
+    match (img_key, sub_key) {
+        (Some(img_key), Some(sub_key)) => Ok(mixin_key(&img_key, &sub_key)),
+        _ => Err(format!("获取 WBI 密钥失败 ({})", json["code"])),
+    }
+}
+
+/// 获取混合密钥（缓存过期时重新获取）
+async fn get_mixin_key() -> Result<String, String> {
+    let mut key_guard = WBI_KEY.lock().await;
+    if let Some(key) = key_guard.as_ref() {
+        if key.fetched_at.elapsed() < Duration::from_secs(WBI_KEYS_TTL_SECS) {
+            return Ok(key.mixin_key.clone());
+        }
+    }
+    
+    let mixin_key = fetch_mixin_key().await?;
+    *key_guard = Some(WbiKey {
+        mixin_key: mixin_key.clone(),
+        fetched_at: Instant::now(),
+    });
+    Ok(mixin_key)
+}
+
+/// 丢弃缓存的密钥（签名被拒绝时调用，下次签名重新获取）
+pub async fn invalidate() {
+    *WBI_KEY.lock().await = None;
+}
+
+/// 用指定的混合密钥和时间戳计算签名后的参数
+fn sign_with(params: &[(String, String)], mixin_key: &str, wts: u64) -> Vec<(String, String)> {
+    let mut signed: Vec<(String, String)> = params
+        .iter()
+        .filter(|(key, _)| key != "w_rid" && key != "wts")
+        .map(|(key, value)| (key.clone(), value.replace(FILTERED_CHARS, "")))
+        .collect();
+    signed.push(("wts".to_string(), wts.to_string()));
+    signed.sort_by(|a, b| a.0.cmp(&b.0));
+    
+    let query = signed
+        .iter()
+        .map(|(key, value)| format!("{}={}", urlencoding::encode(key), urlencoding::encode(value)))
+        .collect::<Vec<_>>()
+        .join("&");
+    let w_rid = format!("{:x}", md5::compute(format!("{}{}", query, mixin_key)));
+    signed.push(("w_rid".to_string(), w_rid));
+    signed
+}
+
+/// 为查询参数添加 WBI 签名（返回需要发送的完整参数）
+pub async fn sign(params: &[(String, String)]) -> Result<Vec<(String, String)>, String> {
+    let mixin_key = get_mixin_key().await?;
+    let wts = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    Ok(sign_with(params, &mixin_key, wts))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn params(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+    
+    /// 公开文档中的 WBI 签名示例
+    #[test]
+    fn published_example() {
+        let img_key = key_from_url("https://i0.hdslb.com/bfs/wbi/7cd084941338484aae1ad9425b84077c.png").unwrap();
+        let sub_key = key_from_url("https://i0.hdslb.com/bfs/wbi/4932caff0ff746eab6f01bf08b70ac45.png").unwrap();
+        let mixin_key = mixin_key(&img_key, &sub_key);
+        assert_eq!(mixin_key, "ea1db124af3c7062474693fa704f4ff8");
+        
+        let signed = sign_with(&params(&[("foo", "114"), ("bar", "514"), ("zab", "1919810")]), &mixin_key, 1702204169);
+        assert_eq!(
+            signed,
+            params(&[
+                ("bar", "514"),
+                ("foo", "114"),
+                ("wts", "1702204169"),
+                ("zab", "1919810"),
+                ("w_rid", "8f6f2b5b3d485fe1886cec6a0be8c5d4"),
+            ])
+        );
+    }
+    
+    /// 重新签名时丢弃旧的 w_rid/wts，参数值中的特殊字符不参与签名
+    #[test]
+    fn resign_filters_values_and_old_signature() {
+        let mixin_key = "ea1db124af3c7062474693fa704f4ff8";
+        let expected = sign_with(&params(&[("foo", "114"), ("bar", "514"), ("zab", "1919810")]), mixin_key, 1702204169);
+        let resigned = sign_with(
+            &params(&[
+                ("foo", "1(1)4"),
+                ("w_rid", "stale"),
+                ("bar", "5'1!4*"),
+                ("wts", "1"),
+                ("zab", "1919810"),
+            ]),
+            mixin_key,
+            1702204169,
+        );
+        assert_eq!(resigned, expected);
+    }
+}
@@ -11,15 +11,19 @@ import {
 } from '@/types'
 import {
   AUDIO_URL_CACHE_SIZE,
+  COVER_IMAGE_SIZE,
   DANKOU_KEYWORDS,
   DUIKOU_KEYWORDS,
   IMAGE_PROXY_PARAMS,
+  IMAGE_URL_CACHE_SIZE,
   PLAYED_VIDEOS_CACHE_SIZE,
+  PROXY_URL_PREFIXES,
 } from '@/constants'
-import type { AudioQuality } from '@/types'
+import type { AudioQuality, PlayItem } from '@/types'
 import { parseDuration, processImageUrl, stripHtmlTags } from '@/utils/video'
 
 const audioUrlCache = new LRUCache<string, string>(AUDIO_URL_CACHE_SIZE)
+const imageUrlCache = new LRUCache<string, string>(IMAGE_URL_CACHE_SIZE)
 
 // 清除音频 URL 缓存（用于链接过期时刷新）
 export function clearAudioUrlCache(url?: string) {
@@ -72,7 +76,7 @@ export async function proxyAudioUrl(url: string): Promise<string> {
         const proxyUrl = await invoke<string>('proxy_audio', { url })
         
         // 验证代理 URL 是否有效
-        if (!proxyUrl || (!proxyUrl.startsWith('http://127.0.0.1:') && !proxyUrl.startsWith('http://localhost:'))) {
+        if (!proxyUrl || !PROXY_URL_PREFIXES.some(prefix => proxyUrl.startsWith(prefix))) {
           throw new Error('代理 URL 无效')
         }
         
@@ -106,17 +110,22 @@ export async function proxyAudioUrl(url: string): Promise<string> {
 // 类型定义已移至 @/types
 // parseDuration 函数已移至 @/utils/video
 
+// 搜索接口路径（Tauri 环境由后端添加 WBI 签名，浏览器环境无法签名只能使用旧接口）
+function searchPath(): string {
+  return checkTauriEnv() ? '/x/web-interface/wbi/search/type' : '/x/web-interface/search/type'
+}
+
 // 搜索相声视频（网络错误会向上抛出）
 async function searchVideos(keyword: string, page = 1): Promise<VideoItem[]> {
   // 先尝试 30-60 分钟时长
-  let res = await apiRequest.get<SearchResponse>('/x/web-interface/search/type', {
+  let res = await apiRequest.get<SearchResponse>(searchPath(), {
     params: { keyword, search_type: 'video', order: 'totalrank', duration: 3, page, page_size: 20 },
   })
 
   let data = res as unknown as SearchResponse
   if (data.code !== 0 || !data.data?.result) {
     // 不限制时长重试
-    res = await apiRequest.get<SearchResponse>('/x/web-interface/search/type', {
+    res = await apiRequest.get<SearchResponse>(searchPath(), {
       params: { keyword, search_type: 'video', order: 'totalrank', page, page_size: 20 },
     })
     data = res as unknown as SearchResponse
@@ -344,19 +353,36 @@ async function getRandomVideoByKeywords(keywords: string[]): Promise<VideoItem |
   return null
 }
 
+// Tauri 环境由后端选取（播放历史持久化，返回已解析播放地址的条目）
+async function pickRandomNative(gangType: 'dan' | 'dui' | 'all'): Promise<PlayItem> {
+  const { invoke } = await import('@tauri-apps/api/core')
+  const { customKeywords, audioQuality } = useSettingsStore.getState()
+  const item = await invoke<PlayItem>('random_pick', {
+    gangType,
+    customKeywords,
+    quality: audioQuality,
+  }).catch((err) => {
+    throw new Error(typeof err === 'string' ? err : err?.message || '随机选取失败')
+  })
+  return { ...item, pages: item.pages?.length ? item.pages : undefined }
+}
+
 // 随机获取一个相声视频（可能是单个或合集）
-export async function getRandomVideo(): Promise<VideoItem | (VideoItem & { pages: VideoItem[] }) | null> {
+export async function getRandomVideo(): Promise<VideoItem | (VideoItem & { pages: VideoItem[] }) | PlayItem | null> {
+  if (checkTauriEnv()) return pickRandomNative('all')
   const keywords = getAllKeywords()
   return getRandomVideoByKeywords(keywords)
 }
 
 // 随机获取一个单口相声视频（可能是单个或合集）
-export async function getRandomDanKouVideo(): Promise<VideoItem | (VideoItem & { pages: VideoItem[] }) | null> {
+export async function getRandomDanKouVideo(): Promise<VideoItem | (VideoItem & { pages: VideoItem[] }) | PlayItem | null> {
+  if (checkTauriEnv()) return pickRandomNative('dan')
   return getRandomVideoByKeywords([...DANKOU_KEYWORDS])
 }
 
 // 随机获取一个对口相声视频（可能是单个或合集）
-export async function getRandomDuiKouVideo(): Promise<VideoItem | (VideoItem & { pages: VideoItem[] }) | null> {
+export async function getRandomDuiKouVideo(): Promise<VideoItem | (VideoItem & { pages: VideoItem[] }) | PlayItem | null> {
+  if (checkTauriEnv()) return pickRandomNative('dui')
   return getRandomVideoByKeywords([...DUIKOU_KEYWORDS])
 }
 
@@ -364,3 +390,36 @@ export async function getRandomDuiKouVideo(): Promise<VideoItem | (VideoItem & {
 export function getProxiedImageUrl(url: string): string {
   return processImageUrl(url, IMAGE_PROXY_PARAMS)
 }
+
+// 已解析的封面地址（没有解析过时返回 undefined）
+export function getCachedImageUrl(url: string): string | undefined {
+  return checkTauriEnv() ? imageUrlCache.get(url) : getProxiedImageUrl(url)
+}
+
+// 封面地址：Tauri 环境通过本地图片代理（缩放并缓存到磁盘），失败时使用 B 站图片参数
+export async function proxyImageUrl(url: string): Promise<string> {
+  if (!url) return ''
+  if (!checkTauriEnv()) return getProxiedImageUrl(url)
+  
+  const cached = imageUrlCache.get(url)
+  if (cached) {
+    return cached
+  }
+  
+  try {
+    const { invoke } = await import('@tauri-apps/api/core')
+    const proxyUrl = await invoke<string>('proxy_image', {
+      url: processImageUrl(url, ''),
+      width: COVER_IMAGE_SIZE,
+      height: COVER_IMAGE_SIZE,
+    })
+    if (!proxyUrl || !PROXY_URL_PREFIXES.some(prefix => proxyUrl.startsWith(prefix))) {
+      throw new Error('代理 URL 无效')
+    }
+    imageUrlCache.set(url, proxyUrl)
+    return proxyUrl
+  } catch (error) {
+    console.error('[Image Proxy] 封面代理失败:', error)
+    return getProxiedImageUrl(url)
+  }
+}
@@ -57,30 +57,12 @@ export const apiRequest = {
     const isTauri = checkTauriEnv()
     
     if (isTauri) {
-      try {
-        const result = await invokeHttpRequest<T>(fullUrl, config?.params)
-        
-        if (result.status >= 200 && result.status < 300) {
-          return result.data
-        } else if (result.status === 412) {
-          // 412错误时，先访问bilibili.com获取cookies，然后重试
-          try {
-            await invokeHttpRequest(BILIBILI_SITE_URL)
-            const retryResult = await invokeHttpRequest<T>(fullUrl, config?.params)
-            
-            if (retryResult.status >= 200 && retryResult.status < 300) {
-              return retryResult.data
-            }
-            throw new Error(`HTTP ${retryResult.status}: ${JSON.stringify(retryResult.data)}`)
-          } catch {
-            throw new Error(`HTTP 412 Precondition Failed: ${JSON.stringify(result.data)}`)
-          }
-        } else {
-          throw new Error(`HTTP ${result.status}: ${JSON.stringify(result.data)}`)
-        }
-      } catch (error) {
-        throw error
+      // 412 和风控响应（-412/-352）由后端统一冷却、限速并重新获取 Cookie，以错误形式返回
+      const result = await invokeHttpRequest<T>(fullUrl, config?.params)
+      if (result.status >= 200 && result.status < 300) {
+        return result.data
       }
+      throw new Error(`HTTP ${result.status}: ${JSON.stringify(result.data)}`)
     }
     
     // 非Tauri环境降级到axios
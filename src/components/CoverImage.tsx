@@ -0,0 +1,38 @@
+import { useEffect, useState } from 'react'
+import { getCachedImageUrl, proxyImageUrl } from '@/api/bilibili'
+
+interface CoverImageProps {
+  pic: string
+  alt: string
+  className?: string
+}
+
+// 视频封面（Tauri 环境通过本地图片代理加载，加载失败时隐藏）
+export function CoverImage({ pic, alt, className }: CoverImageProps) {
+  const [src, setSrc] = useState(() => getCachedImageUrl(pic))
+
+  useEffect(() => {
+    let cancelled = false
+    setSrc(getCachedImageUrl(pic))
+    proxyImageUrl(pic).then((url) => {
+      if (!cancelled) setSrc(url)
+    })
+    return () => {
+      cancelled = true
+    }
+  }, [pic])
+
+  if (!src) return null
+
+  return (
+    <img
+      src={src}
+      alt={alt}
+      className={className}
+      referrerPolicy="no-referrer"
+      onError={(e) => {
+        (e.target as HTMLImageElement).style.display = 'none'
+      }}
+    />
+  )
+}
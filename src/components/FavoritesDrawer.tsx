@@ -1,9 +1,10 @@
 import { useFavoritesStore, type FavoriteItem } from '@/store/favorites'
 import { usePlayerStore } from '@/store/player'
-import { getProxiedImageUrl, getAudioUrl, proxyAudioUrl, getVideoInfo, checkVideoStatus } from '@/api/bilibili'
+import { getAudioUrl, proxyAudioUrl, getVideoInfo, checkVideoStatus } from '@/api/bilibili'
 import { formatDuration } from '@/utils/format'
 import type { PlayItem } from '@/types'
 import { Drawer } from './Drawer'
+import { CoverImage } from './CoverImage'
 import { isTauri, isAndroid } from '@/utils/platform'
 import { useEffect } from 'react'
 
@@ -143,15 +144,7 @@ function FavoritesDrawer({ isOpen, onClose }: FavoritesDrawerProps) {
                 className="group flex items-center gap-3 p-3 rounded-lg hover:bg-white/5 transition-colors cursor-pointer"
               >
                 <div className="w-14 h-14 rounded-md overflow-hidden flex-shrink-0 bg-white/5">
-                  <img
-                    src={getProxiedImageUrl(favorite.pic)}
-                    alt={favorite.title}
-                    className="w-full h-full object-cover"
-                    referrerPolicy="no-referrer"
-                    onError={(e) => {
-                      (e.target as HTMLImageElement).style.display = 'none'
-                    }}
-                  />
+                  <CoverImage pic={favorite.pic} alt={favorite.title} className="w-full h-full object-cover" />
                 </div>
 
                 <div className="flex-1 min-w-0">
@@ -2,7 +2,8 @@ import { useState, useEffect } from 'react'
 import { usePlayerStore } from '@/store/player'
 import { useSettingsStore } from '@/store/settings'
 import { useFavoritesStore } from '@/store/favorites'
-import { getProxiedImageUrl, getAudioUrl } from '@/api/bilibili'
+import { getAudioUrl } from '@/api/bilibili'
+import { CoverImage } from './CoverImage'
 import { platformAPI } from '@/utils/platform'
 import { formatDuration } from '@/utils/format'
 import logoImage from '@/assets/guodegang.svg'
@@ -146,15 +147,7 @@ function Playlist() {
             )}
 
             <div className="relative w-10 h-10 rounded-md overflow-hidden flex-shrink-0 bg-white/5">
-              <img
-                src={getProxiedImageUrl(item.pic)}
-                alt={item.title}
-                className="w-full h-full object-cover"
-                referrerPolicy="no-referrer"
-                onError={(e) => {
-                  (e.target as HTMLImageElement).style.display = 'none'
-                }}
-              />
+              <CoverImage pic={item.pic} alt={item.title} className="w-full h-full object-cover" />
               {isDownloading && (
                 <div className="absolute inset-0 bg-black/50 flex items-center justify-center">
                   <span className="text-[10px] text-white font-medium">{downloadProgress}%</span>
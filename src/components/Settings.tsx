@@ -2,7 +2,7 @@ import { useState, useEffect } from 'react'
 import { useSettingsStore } from '@/store/settings'
 import { useFavoritesStore } from '@/store/favorites'
 import { usePlayerStore } from '@/store/player'
-import { getProxiedImageUrl } from '@/api/bilibili'
+import { CoverImage } from './CoverImage'
 import { platformAPI } from '@/utils/platform'
 import { formatDuration } from '@/utils/format'
 
@@ -410,15 +410,7 @@ function Settings() {
                     className="group flex items-center gap-3 p-2 rounded-lg hover:bg-white/5 transition-colors cursor-pointer"
                   >
                     <div className="w-12 h-12 rounded-md overflow-hidden flex-shrink-0 bg-white/5">
-                      <img
-                        src={getProxiedImageUrl(favorite.pic)}
-                        alt={favorite.title}
-                        className="w-full h-full object-cover"
-                        referrerPolicy="no-referrer"
-                        onError={(e) => {
-                          (e.target as HTMLImageElement).style.display = 'none'
-                        }}
-                      />
+                      <CoverImage pic={favorite.pic} alt={favorite.title} className="w-full h-full object-cover" />
                     </div>
 
                     <div className="flex-1 min-w-0">
@@ -52,5 +52,14 @@ export const PLAYED_VIDEOS_CACHE_SIZE = 100
 // 音频 URL 缓存大小
 export const AUDIO_URL_CACHE_SIZE = 10
 
+// 本地代理地址前缀（代理可配置为 IPv6 回环地址）
+export const PROXY_URL_PREFIXES = ['http://127.0.0.1:', 'http://localhost:', 'http://[::1]:'] as const
+
 // 图片代理参数
 export const IMAGE_PROXY_PARAMS = '@300w_300h_1c.webp'
+
+// 封面缩放尺寸（本地图片代理）
+export const COVER_IMAGE_SIZE = 300
+
+// 封面代理 URL 缓存大小
+export const IMAGE_URL_CACHE_SIZE = 200
@@ -1,6 +1,8 @@
 import { create } from 'zustand'
 import { persist } from 'zustand/middleware'
 import type { FavoriteItem, VideoItem } from '@/types'
+import { useSettingsStore } from '@/store/settings'
+import { platformAPI, isTauri } from '@/utils/platform'
 
 // 重新导出类型以便向后兼容
 export type { FavoriteItem }
@@ -60,3 +62,21 @@ export const useFavoritesStore = create<FavoritesState & FavoritesActions>()(
   )
 )
 
+// 同步媒体库到后端（供 DLNA 媒体服务和播客订阅使用）
+function syncMediaLibrary() {
+  if (!isTauri || !platformAPI.setMediaLibrary) return
+  const { favorites } = useFavoritesStore.getState()
+  const { downloadPath } = useSettingsStore.getState()
+  platformAPI.setMediaLibrary(favorites, downloadPath).catch(() => {
+    // 忽略错误
+  })
+}
+
+// 启动时同步一次，之后收藏或下载目录变化时重新同步
+syncMediaLibrary()
+useFavoritesStore.subscribe((state, prev) => {
+  if (state.favorites !== prev.favorites) syncMediaLibrary()
+})
+useSettingsStore.subscribe((state, prev) => {
+  if (state.downloadPath !== prev.downloadPath) syncMediaLibrary()
+})
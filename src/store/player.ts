@@ -9,7 +9,7 @@ import type { PlayItem, VideoItem } from '@/types'
 let refreshRetryCount = 0
 const MAX_REFRESH_RETRIES = 2
 
-type VideoResult = VideoItem | VideoItem[] | (VideoItem & { pages?: VideoItem[] })
+type VideoResult = VideoItem | VideoItem[] | (VideoItem & { pages?: VideoItem[] }) | PlayItem
 
 interface PlayerStore {
   isPlaying: boolean
@@ -89,6 +89,9 @@ const playAudio = (url: string) => {
 
 // 处理视频结果，获取音频URL
 const processVideoResult = async (result: VideoResult): Promise<PlayItem | null> => {
+  // 后端选取的条目已包含播放地址
+  if ('audioUrl' in result && result.audioUrl) return result
+
   if (Array.isArray(result)) {
     const first = result[0]
     if (!first?.cid) return null
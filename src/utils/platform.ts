@@ -2,6 +2,7 @@ import { Capacitor } from '@capacitor/core'
 import { Filesystem, Directory } from '@capacitor/filesystem'
 import { invoke } from '@tauri-apps/api/core'
 import { listen } from '@tauri-apps/api/event'
+import type { FavoriteItem } from '@/types'
 
 // 检测是否在 Tauri 环境
 export function checkTauriEnv(): boolean {
@@ -43,6 +44,7 @@ export interface PlatformAPI {
   onUpdateStatus?: (callback: (status: any) => void) => () => void
   setCloseAction?: (action: 'quit' | 'hide') => Promise<void>
   getCloseAction?: () => Promise<'quit' | 'hide'>
+  setMediaLibrary?: (favorites: FavoriteItem[], downloadDir?: string) => Promise<void>
 }
 
 // Tauri 平台实现
@@ -170,6 +172,24 @@ const tauriAPI: PlatformAPI = {
     } catch {
       return 'quit'
     }
+  },
+  setMediaLibrary: async (favorites: FavoriteItem[], downloadDir?: string) => {
+    // 后端按分 P 提供播放地址，没有 cid 的收藏无法播放，不同步
+    const tracks = favorites
+      .filter(f => f.cid)
+      .map(f => ({
+        bvid: f.bvid,
+        cid: f.cid,
+        title: f.title,
+        pic: f.pic,
+        duration: f.duration,
+        addedAt: f.addedAt,
+      }))
+    try {
+      await invoke('set_media_library', { favorites: tracks, downloadDir: downloadDir || null })
+    } catch {
+      // 忽略错误
+    }
   }
 }
 